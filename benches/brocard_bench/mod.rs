@@ -11,7 +11,7 @@ use super::*;
 use criterion::{Criterion, BenchmarkId};
 
 use brocard::math::prime::primes_from;
-use brocard::brocard::span::BrocardSpan;
+use brocard::brocard::span::{Backend, BrocardSpan, Layout};
 
 
 #[criterion(config())]
@@ -27,7 +27,7 @@ fn brocard_test(c: &mut Criterion) {
 
     for val in vals {
         let primes : Vec<u128> = primes_from(val).take(60).collect();
-        let (tx, rx) = crossbeam::channel::unbounded();
+        let (tx, _rx) = crossbeam::channel::unbounded();
 
 
         let span = BrocardSpan::new(2, val, primes, tx);
@@ -40,3 +40,61 @@ fn brocard_test(c: &mut Criterion) {
     }
     group.finish();
 }
+
+/// The same spans as above, solved once per residue layout.
+#[criterion(config())]
+fn brocard_layout_test(c: &mut Criterion) {
+
+    let mut group = c.benchmark_group("Brocard Span Solver by Layout");
+
+    let vals = vec![
+        10_000,
+        20_000,
+        30_000
+    ];
+
+    for val in vals {
+        for layout in [Layout::Elementwise, Layout::Batched] {
+            let primes : Vec<u128> = primes_from(val).take(60).collect();
+            let (tx, _rx) = crossbeam::channel::unbounded();
+
+            let span = BrocardSpan::new(2, val, primes, tx).with_layout(layout);
+
+            group.bench_with_input(BenchmarkId::new(format!("{:?}", layout), val), &val, |bench, _val| {
+                bench.iter(|| {
+                    span.solve();
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+/// The same spans again, solved once per modular arithmetic backend.
+#[criterion(config())]
+fn brocard_backend_test(c: &mut Criterion) {
+
+    let mut group = c.benchmark_group("Brocard Span Solver by Backend");
+
+    let vals = vec![
+        10_000,
+        20_000,
+        30_000
+    ];
+
+    for val in vals {
+        for backend in [Backend::Montgomery, Backend::Barrett, Backend::Naive] {
+            let primes : Vec<u128> = primes_from(val).take(60).collect();
+            let (tx, _rx) = crossbeam::channel::unbounded();
+
+            let span = BrocardSpan::new(2, val, primes, tx).with_backend(backend);
+
+            group.bench_with_input(BenchmarkId::new(format!("{:?}", backend), val), &val, |bench, _val| {
+                bench.iter(|| {
+                    span.solve();
+                });
+            });
+        }
+    }
+    group.finish();
+}
@@ -1,10 +1,10 @@
-/// This calculates the legendre symbol of a random value based on the input seed using different
-/// multiplication methods.
+//! This calculates the legendre symbol of a random value based on the input seed using different
+//! multiplication methods.
 
 use super::*;
 use criterion::{black_box, BenchmarkId, Criterion};
 
-use brocard::math::legendre::LegendreSymbol;
+use brocard::math::legendre::{jacobi, LegendreSymbol};
 use brocard::montgomery::Space;
 
 const RANGE : std::ops::Range<u128> = 10_000..20_000;
@@ -84,3 +84,125 @@ fn legendre_bench(c: &mut Criterion) {
 
     group.finish();
 }
+
+/// Compares the word backends on identical inputs. The prime has to fit in the narrowest word, so
+/// this uses the largest 32b prime rather than the Mersenne prime above.
+#[criterion(config())]
+fn legendre_word_bench(c: &mut Criterion) {
+    let p = 4_294_967_291;
+
+    let mut group = c.benchmark_group("Legendre Symbol by Word");
+
+    let space = Space::<32, u32>::new(p);
+    group.bench_with_input(BenchmarkId::new("Montgomery", "u32, R_EXP = 32"), &p, |bench, _p| {
+        bench.iter(|| {
+            for a in RANGE {
+                black_box(space.legendre(space.enter(a)));
+            }
+        });
+    });
+
+    let space = Space::<64, u64>::new(p);
+    group.bench_with_input(BenchmarkId::new("Montgomery", "u64, R_EXP = 64"), &p, |bench, _p| {
+        bench.iter(|| {
+            for a in RANGE {
+                black_box(space.legendre(space.enter(a)));
+            }
+        });
+    });
+
+    let space = Space::<64, u128>::new(p);
+    group.bench_with_input(BenchmarkId::new("Montgomery", "u128, R_EXP = 64"), &p, |bench, _p| {
+        bench.iter(|| {
+            for a in RANGE {
+                black_box(space.legendre(space.enter(a)));
+            }
+        });
+    });
+
+    let space = Space::<128, u128>::new(p);
+    group.bench_with_input(BenchmarkId::new("Montgomery", "u128, R_EXP = 128"), &p, |bench, _p| {
+        bench.iter(|| {
+            for a in RANGE {
+                black_box(space.legendre(space.enter(a)));
+            }
+        });
+    });
+
+    group.finish();
+}
+
+/// Euler's criterion against the binary Jacobi algorithm, both from inside a space (so the Jacobi
+/// side pays for its exit) and on plain integers.
+#[criterion(config())]
+fn legendre_vs_jacobi_bench(c: &mut Criterion) {
+    let p = (1 << 61) - 1;
+
+    let mut group = c.benchmark_group("Legendre Symbol, Euler vs Jacobi");
+
+    let space = Space::<64, u64>::new(p);
+
+    group.bench_with_input(BenchmarkId::new("Euler", "Montgomery u64"), &p, |bench, _p| {
+        bench.iter(|| {
+            for a in RANGE {
+                black_box(space.legendre(space.enter(a)));
+            }
+        });
+    });
+
+    group.bench_with_input(BenchmarkId::new("Jacobi", "Montgomery u64"), &p, |bench, _p| {
+        bench.iter(|| {
+            for a in RANGE {
+                black_box(space.jacobi(space.enter(a)));
+            }
+        });
+    });
+
+    group.bench_with_input(BenchmarkId::new("Jacobi", "Plain"), &p, |bench, p| {
+        bench.iter(|| {
+            for a in RANGE {
+                black_box(jacobi(a, *p));
+            }
+        });
+    });
+
+    group.finish();
+}
+
+/// The exponentiation at the heart of Euler's criterion: the binary ladder, a sliding window
+/// planned per call, and the plan the space caches for `(p-1)/2`.
+#[criterion(config())]
+fn legendre_exponentiation_bench(c: &mut Criterion) {
+    let p = (1 << 61) - 1;
+    let e = (p - 1) >> 1;
+
+    let mut group = c.benchmark_group("Legendre Exponentiation");
+
+    let space = Space::<64, u64>::new(p);
+
+    group.bench_with_input(BenchmarkId::new("Binary Ladder", "u64"), &e, |bench, e| {
+        bench.iter(|| {
+            for a in RANGE {
+                black_box(space.enter(a).exp(*e));
+            }
+        });
+    });
+
+    group.bench_with_input(BenchmarkId::new("Sliding Window", "u64"), &e, |bench, e| {
+        bench.iter(|| {
+            for a in RANGE {
+                black_box(space.enter(a).exp_sliding(*e));
+            }
+        });
+    });
+
+    group.bench_with_input(BenchmarkId::new("Cached Plan", "u64"), &e, |bench, _e| {
+        bench.iter(|| {
+            for a in RANGE {
+                black_box(space.enter(a).exp_with(space.legendre_plan.as_ref().unwrap()));
+            }
+        });
+    });
+
+    group.finish();
+}
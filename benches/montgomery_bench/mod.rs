@@ -4,6 +4,7 @@ use rand::rngs::StdRng;
 use rand::SeedableRng;
 
 use brocard::math::{gcd, mod_mult};
+use brocard::montgomery::Word;
 use quickcheck::{Arbitrary, StdGen, Gen};
 
 // FIXME: This sucks, copied from test_case
@@ -12,19 +13,21 @@ pub struct TestCase {
     pub(crate) a: u128,
     pub(crate) b: u128,
     pub(crate) n: u128,
+    #[allow(dead_code)]
     pub(crate) r_exp: usize
 }
 
 impl Arbitrary for TestCase {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
-        // r_exp is in [1,127] to avoid overflows
-        let r_exp = 1 + usize::arbitrary(g) % 126;
+        // r_exp is in [2,126] to avoid overflows, and so that there's room for an `n` above 1
+        let r_exp = 2 + usize::arbitrary(g) % 125;
         let r = 1 << r_exp;
 
-        // We'll just hunt till we find a coprime `n`, should be fast, any odd number will
-        // do. We also need `n < r`, so we can just examine `n mod r` to ensure this.
+        // We'll just hunt till we find a coprime `n`, should be fast, any odd number but 1 will
+        // do (see `SpaceError`). We also need `n < r`, so we can just examine `n mod r` to ensure
+        // this.
         let mut n = u128::arbitrary(g) % r;
-        while gcd(n, r) != 1 {
+        while gcd(n, r) != 1 || n == 1 {
             n = u128::arbitrary(g) % r;
         }
 
@@ -92,6 +95,48 @@ fn montgomery_multiplication_fixed_r_exp(c: &mut Criterion) {
 
 }
 
+/// The same multiplications in each word backend. `TestCase` keeps its moduli small, so every
+/// case fits in every word and the inputs are shared across the group. The spaces and operands
+/// are all built before timing starts, so only the multiplications are measured.
+#[criterion(config())]
+fn montgomery_multiplication_by_word(c: &mut Criterion) {
+    const CASES: usize = 256;
+
+    let mut rng = StdRng::seed_from_u64(get_seed());
+    let mut gen = StdGen::new(&mut rng, 1000);
+    let cases : Vec<TestCase> = (0..CASES).map(|_| TestCase::arbitrary(&mut gen)).collect();
+
+    let mut group = c.benchmark_group("Montgomery Multiplication by Word");
+
+    bench_word::<32, u32>(&mut group, "u32, R_EXP = 32", &cases);
+    bench_word::<64, u64>(&mut group, "u64, R_EXP = 64", &cases);
+    bench_word::<128, u128>(&mut group, "u128, R_EXP = 128", &cases);
+
+    group.finish();
+}
+
+/// Times one pass of raw Montgomery multiplications over `cases`, with each case's space and
+/// operands (already in Montgomery form) set up beforehand.
+fn bench_word<const R_EXP: usize, W: Word>(
+    group: &mut criterion::BenchmarkGroup<criterion::measurement::WallTime>,
+    name: &str,
+    cases: &[TestCase],
+) {
+    let spaces : Vec<(Space<R_EXP, W>, W, W)> = cases.iter().map(|&TestCase { a, b, n, r_exp: _ }| {
+        let space = Space::<R_EXP, W>::new(n);
+        let (a, b) = (space.enter(a).val, space.enter(b).val);
+        (space, a, b)
+    }).collect();
+
+    group.bench_function(BenchmarkId::new("Word", name), |bench| {
+        bench.iter(|| {
+            for (space, a, b) in &spaces {
+                black_box(space.mul(black_box(*a), black_box(*b)));
+            }
+        });
+    });
+}
+
 #[criterion(config())]
 fn naive_multiplication(c: &mut Criterion) {
     let mut rng = StdRng::seed_from_u64(get_seed());
@@ -104,3 +149,20 @@ fn naive_multiplication(c: &mut Criterion) {
     }));
 }
 
+
+#[criterion(config())]
+fn factorial_forward_vs_sublinear(c: &mut Criterion) {
+    let p = (1 << 61) - 1;
+    let space = Space::<64, u64>::new(p);
+
+    let mut group = c.benchmark_group("Factorial mod a 61b prime");
+    for n in [1u128 << 16, 1 << 20, 1 << 24] {
+        group.bench_with_input(BenchmarkId::new("Forward", n), &n, |bench, &n| {
+            bench.iter(|| black_box(space.factorial(n).exit()));
+        });
+        group.bench_with_input(BenchmarkId::new("Sampled", n), &n, |bench, &n| {
+            bench.iter(|| black_box(space.sampled_factorial(n).unwrap().exit()));
+        });
+    }
+    group.finish();
+}
@@ -1,7 +1,8 @@
 use super::*;
 use criterion::{Criterion, BenchmarkId};
 
-use brocard::math::prime::{is_prime, segmented_seive};
+use brocard::math::prime::{is_prime, primes_from, segmented_seive};
+use brocard::math::{mod_mult, mod_mult_reference};
 
 
 /// This benchmark tests the MR implementation against 4 different values, two are primes, two are
@@ -49,3 +50,75 @@ fn segmented_seive_test(c: &mut Criterion) {
     }
     group.finish();
 }
+
+/// What the broker does for every chunk: find the first 60 odd primes above a bound. This pits
+/// the sieve behind `primes_from` against filtering the integers through `is_prime`, including one
+/// bound past `SIEVE_LIMIT`, where the sieve only contributes its wheel.
+#[criterion(config())]
+fn primes_above_test(c: &mut Criterion) {
+    let mut group = c.benchmark_group("First 60 Primes Above");
+
+    let bounds : [u128; 4] = [
+        100_000_000,
+        1_000_000_000,
+        50_000_000_000,
+        5_000_000_000_000,
+    ];
+
+    for bound in bounds {
+        group.bench_with_input(BenchmarkId::new("sieve", bound), &bound, |bench, &bound| {
+            bench.iter(|| {
+                primes_from(bound).filter(|n| n & 1 == 1).take(60).last()
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("is_prime", bound), &bound, |bench, &bound| {
+            bench.iter(|| {
+                (bound..).filter(|&n| is_prime(n)).filter(|n| n & 1 == 1).take(60).last()
+            });
+        });
+    }
+    group.finish();
+}
+
+/// `mod_mult` backs `mod_exp`, and so every Miller-Rabin round. This pits the widening multiply
+/// and 256/128 reduction against the old double-and-add loop, for a single multiply and for the
+/// exponentiation a Miller-Rabin round does, at a few sizes of modulus.
+#[criterion(config())]
+fn modular_multiplication_test(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Modular Multiplication");
+
+    let moduli : [u128; 3] = [
+        (1 << 61) - 1,
+        0xFFFF_FFFF_FFFF_FFC5, // the largest prime below 2^64
+        (1 << 127) - 1,
+    ];
+
+    // The ladder from `mod_exp`, over either multiply.
+    fn ladder(mul: fn(u128, u128, u128) -> u128, a: u128, k: u128, n: u128) -> u128 {
+        let (mut a, mut k, mut result) = (a, k, 1);
+        while k > 0 {
+            if k & 1 == 1 { result = mul(result, a, n); }
+            a = mul(a, a, n);
+            k >>= 1;
+        }
+        result
+    }
+
+    for n in moduli {
+        let (a, b) = (n / 3, n / 7);
+
+        group.bench_with_input(BenchmarkId::new("mod_mult", n), &n, |bench, &n| {
+            bench.iter(|| black_box(mod_mult(black_box(a), black_box(b), n)));
+        });
+        group.bench_with_input(BenchmarkId::new("mod_mult_reference", n), &n, |bench, &n| {
+            bench.iter(|| black_box(mod_mult_reference(black_box(a), black_box(b), n)));
+        });
+        group.bench_with_input(BenchmarkId::new("mod_exp", n), &n, |bench, &n| {
+            bench.iter(|| black_box(ladder(mod_mult, black_box(a), n - 1, n)));
+        });
+        group.bench_with_input(BenchmarkId::new("mod_exp_reference", n), &n, |bench, &n| {
+            bench.iter(|| black_box(ladder(mod_mult_reference, black_box(a), n - 1, n)));
+        });
+    }
+    group.finish();
+}
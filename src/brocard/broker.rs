@@ -1,18 +1,39 @@
-use crate::math::gcd;
-use crate::math::prime::*;
 use crossbeam::channel::{Receiver, Sender};
+use std::ops::RangeInclusive;
 use std::time::Duration;
 
 use crate::brocard::span::BrocardSpan;
 use crate::brocard::report::BrocardReport;
 use crate::brocard::candidate::BrocardCandidate;
-
+use crate::brocard::handoff::Handoff;
+use crate::brocard::source::{NextPrimes, PrimeSource};
+use crate::brocard::verify::VERIFY_LIMIT;
+
+
+/// How the broker lays its chunks out over the search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Schedule {
+    /// Every chunk gets its own primes, just above its end, and builds its factorials from
+    /// scratch.
+    #[default]
+    Independent,
+    /// The search is cut into windows of `window` candidates, each with one set of primes just
+    /// above its end. The chunks in a window run one after another, each picking up the
+    /// factorials the last one finished with. After every chunk the hand-off is written to
+    /// `./out/handoff-<window start>.txt`, and a broker restarted over the same search picks the
+    /// window up from there.
+    Carried { window: u128 },
+}
 
 pub struct BrocardBroker {
     start: u128,
     span: u128,
     chunk_size: usize,
     target_time: std::time::Duration,
+    schedule: Schedule,
+    source: Box<dyn PrimeSource>,
+    certify: bool,
+    verify_limit: u128,
     rx: Receiver<BrocardReport>,
     tx: Sender<BrocardReport>
 }
@@ -30,11 +51,76 @@ impl BrocardBroker {
             span,
             chunk_size,
             target_time,
+            schedule: Schedule::default(),
+            source: Box::new(NextPrimes),
+            certify: false,
+            verify_limit: VERIFY_LIMIT,
             rx,
             tx
         }
     }
 
+    pub fn with_schedule(mut self, schedule: Schedule) -> BrocardBroker {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Where each chunk's primes come from; `NextPrimes` unless set.
+    pub fn with_source(mut self, source: impl PrimeSource + 'static) -> BrocardBroker {
+        self.source = Box::new(source);
+        self
+    }
+
+    /// Has every report carry a primality certificate for each of its primes, so a run can be
+    /// audited without trusting our Miller-Rabin bases.
+    pub fn with_certificates(mut self, certify: bool) -> BrocardBroker {
+        self.certify = certify;
+        self
+    }
+
+    /// Solutions up to `limit` are checked with exact arithmetic by the worker that found them
+    /// (see `verify`), and the verdicts go in their reports; `VERIFY_LIMIT` unless set, and never
+    /// above it.
+    pub fn with_verify_limit(mut self, limit: u128) -> BrocardBroker {
+        self.verify_limit = limit;
+        self
+    }
+
+    /// Gives `span` the broker's settings: the source's strategy, certificates, and the limit for
+    /// checking solutions exactly.
+    fn configure(&self, span: BrocardSpan) -> BrocardSpan {
+        span.with_strategy(self.source.strategy())
+            .with_certificates(self.certify)
+            .with_verify_limit(self.verify_limit)
+    }
+
+    /// The end of the carried window that a chunk ending at `n` belongs to. Window `k` covers
+    /// `[start + k * window, start + (k+1) * window]`, so its chunks end anywhere past the first
+    /// candidate up to and including the last.
+    fn window_end(&self, n: u128, window: u128) -> u128 {
+        self.start + ((n - self.start - 1) / window + 1) * window
+    }
+
+    /// The span for a chunk that resumes a carried window after `n`, so that it stops at the
+    /// window's end at the latest. `n` has to be short of the end.
+    fn resume_size(&self, n: u128, window_end: u128) -> u128 {
+        (self.chunk_size as u128).min(window_end - n - 1)
+    }
+
+    /// What a failed chunk leaves unsearched: its own candidates, and under `Carried`, the rest of
+    /// its window, since the chunks after it would have picked up from its hand-off.
+    fn unsearched(&self, report: &BrocardReport) -> RangeInclusive<u128> {
+        let range = &report.range;
+        match self.schedule {
+            Schedule::Independent => range.clone(),
+            Schedule::Carried { window } => *range.start()..=self.window_end(*range.end(), window),
+        }
+    }
+
+    fn handoff_path(window_start: u128) -> String {
+        format!("./out/handoff-{}.txt", window_start)
+    }
+
     pub fn run_solver(&mut self, prime_count: usize) {
         println!("Building Threadpool");
 
@@ -52,12 +138,20 @@ impl BrocardBroker {
 
         let mut started_jobs = 0;
         let mut total_checked = 0;
+        // Carried windows send several reports per job, so reports are numbered as they arrive.
+        let mut received_reports = 0;
         let mut backoff = workload_max;
+        // Candidates that failed chunks took down with them.
+        let mut unsearched = vec![];
+
+        // Carried windows only move on when the broker hands each chunk's factorials to the next,
+        // so once every window has been started we stay around until they're all done.
+        let mut draining = false;
 
         loop {
             // if the pool is saturated (all threads are busy), then we need to wait for a job to
             // finish
-            if active_jobs >= workload_max {
+            if active_jobs >= workload_max || (draining && active_jobs > 0) {
                 // TODO: The receiver should be on it's own thread, outside the pool. The broker
                 // just starts and manages those two threads.
                 loop {
@@ -66,16 +160,35 @@ impl BrocardBroker {
                     // new jobs?
                     // FIXME: start_time is kinda busted, I want this to be the actual start time,
                     // but Instant doesn't work that way
-                    if let Ok(ref report@BrocardReport { ref candidates, start_time: _, primes: _, duration  } ) = self.rx.try_recv() {
+                    if let Ok(ref report@BrocardReport { ref candidates, start_time: _, ref primes, duration, error, ref handoff, .. } ) = self.rx.try_recv() {
                         // TODO: impl Display for stuff instead of picking it apart here.
                         println!("Received Report from chunk started {:?} ago.", duration);
 
+                        if let Some(e) = error {
+                            println!("Chunk failed: {}", e);
+                            println!("Primes were: {:?}", primes);
+
+                            let mut failed = report.clone();
+                            failed.unsearched = Some(self.unsearched(report));
+                            println!("Candidates {:?} will not be searched.", failed.unsearched.as_ref().unwrap());
+                            if let Err(e) = failed.write_to_file(format!("./out/report-{}.json", received_reports).as_str()) {
+                                println!("Failed to write chunk due to: {:?}", e);
+                            }
+                            received_reports += 1;
+                            unsearched.extend(failed.unsearched);
+                            println!();
+
+                            active_jobs -= 1;
+                            break;
+                        }
+
                         print!("Writing report to compressed file... ");
                         // TODO: This feels kind of crappy.
                         // TODO: This should also compress the file.
                         let write_result = report.write_to_file(
-                            format!("./out/report-{}.json", started_jobs - workload_max).as_str()
+                            format!("./out/report-{}.json", received_reports).as_str()
                         );
+                        received_reports += 1;
 
                         match write_result {
                             Ok(_) => println!("Done."),
@@ -101,12 +214,18 @@ impl BrocardBroker {
                         }
 
                         println!("Found {} Solutions", sols);
+                        for (n, verdict) in &report.verdicts {
+                            println!("{}! + 1 is {}", n, verdict);
+                        }
 
                         println!("Found {} Nonsolutions", candidates.len() - sols);
                         println!("Nonsolution that passed the most tests: {} with {}/{}", max, max_passed, prime_count);
 
                         total_checked += candidates.len() as u128;
 
+                        self.source.record(report);
+                        println!("Legendre tests per candidate: {:.3}", report.mean_tests());
+
                         println!("Adjusting Size to match target time.");
                         let delta = duration.abs_diff(self.target_time);
                         println!("Current Delta: {:?}", delta);
@@ -128,7 +247,7 @@ impl BrocardBroker {
                             // the previous iteration, and it should be good enough to tune the
                             // thing.
                             if duration > self.target_time {
-                                self.chunk_size = self.chunk_size / 2;
+                                self.chunk_size /= 2;
                             } else {
                                 self.chunk_size *= 3;
                             }
@@ -140,48 +259,178 @@ impl BrocardBroker {
                         }
 
                         println!("Chunk size is currently: {}.", self.chunk_size);
-                        println!("Remaining Chunks: {}", (self.span - total_checked) / self.chunk_size as u128);
+                        println!("Remaining Chunks: {}", self.span.saturating_sub(total_checked) / self.chunk_size as u128);
                         println!("Total Chunks: {}%", (total_checked as f64 / self.span as f64) * 100.0);
                         println!();
 
+                        if let (Schedule::Carried { window }, Some(handoff)) = (self.schedule, handoff) {
+                            let window_end = self.window_end(handoff.n, window);
+
+                            if let Err(e) = handoff.write_to_file(&Self::handoff_path(window_end - window)) {
+                                println!("Failed to record hand-off due to: {:?}", e);
+                            }
+
+                            // The window keeps its slot until its last chunk comes back.
+                            if handoff.n < window_end {
+                                println!("Carrying window ending {} on from {}", window_end, handoff.n);
+                                let size = self.resume_size(handoff.n, window_end);
+                                // Same primes as the chunk just back, so its certificates still hold.
+                                let next = self.configure(BrocardSpan::resume(handoff.clone(), size, self.tx.clone()))
+                                    .with_known_certificates(report.certificates.clone());
+                                pool.spawn(move || next.solve());
+                                continue;
+                            }
+                        }
+
                         // decrement the active jobs counter
                         active_jobs -= 1;
                         // since we have room to schedule more jobs, go do that.
                         break;
                     } 
                     // if we have room to schedule more jobs, go do that.
-                    if active_jobs < workload_max { break; }
+                    if active_jobs < workload_max && !draining { break; }
                 }
             }
 
+            if draining {
+                if active_jobs == 0 { break; }
+                continue;
+            }
 
-            // TODO: Implement take_primes, which gets the first `prime_count` primes larger than
-            // the given value
 
             println!("Preparing Chunk #{}", started_jobs);
-            let next_start = self.start + (started_jobs * self.chunk_size) as u128;
+            let next_start = match self.schedule {
+                Schedule::Independent => self.start + (started_jobs * self.chunk_size) as u128,
+                Schedule::Carried { window } => self.start + started_jobs as u128 * window,
+            };
             println!("Next chunk will start at {}", next_start);
 
             active_jobs += 1;
             started_jobs += 1;
 
+            if next_start > self.start + self.span {
+                println!("Finished all chunks.");
+                active_jobs -= 1;
+                match self.schedule {
+                    Schedule::Independent => break,
+                    Schedule::Carried { .. } => {
+                        draining = true;
+                        continue;
+                    }
+                }
+            }
 
-            println!("Finding Primes");
-            // FIXME: remove magic number (shoudl be R_EXP)
-            let primes : Vec<u128> = primes_from(next_start + self.chunk_size as u128 + 1)
-                                     .filter(|&n| gcd(n, 64) == 1)
-                                     .take(prime_count).collect();
+            let bound = match self.schedule {
+                Schedule::Independent => next_start + self.chunk_size as u128,
+                Schedule::Carried { window } => next_start + window,
+            };
+            println!("Finding Primes above {}", bound);
+            let Some(primes) = self.source.primes(bound, prime_count) else {
+                println!("The prime source has no {} primes above {}; no more chunks will be started.", prime_count, bound);
+                active_jobs -= 1;
+                draining = true;
+                continue;
+            };
 
             let tx_0 = self.tx.clone();
-            let span = BrocardSpan::new(next_start, self.chunk_size as u128, primes, tx_0);
+            let span = match self.schedule {
+                Schedule::Independent => BrocardSpan::new(next_start, self.chunk_size as u128, primes, tx_0),
+                Schedule::Carried { window } => {
+                    let window_end = bound;
+                    let size = (self.chunk_size as u128).clamp(1, window);
+
+                    match Handoff::read_from_file(&Self::handoff_path(next_start)) {
+                        Ok(h) if h.primes() == primes && (next_start..=window_end).contains(&h.n) => {
+                            if h.n == window_end {
+                                println!("Window was already finished by an earlier run.");
+                                active_jobs -= 1;
+                                continue;
+                            }
+                            println!("Resuming window from the recorded hand-off at {}", h.n);
+                            let size = self.resume_size(h.n, window_end);
+                            BrocardSpan::resume(h, size, tx_0)
+                        }
+                        _ => BrocardSpan::new(next_start, size, primes, tx_0),
+                    }
+                }
+            };
 
-            if next_start > self.start + self.span {
-                println!("Finished all chunks.");
-                break;
-            }
+            let span = self.configure(span);
 
             println!("Starting Solve for Chunk #{}", started_jobs);
             pool.spawn(move || span.solve());
         }
+
+        if !unsearched.is_empty() {
+            println!("Failed chunks left these candidates unsearched: {:?}", unsearched);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brocard::verify::Verdict;
+    use crate::math::bigint::BigUint;
+    use crate::math::prime::primes_from;
+    use crate::montgomery::test_case::through_file;
+
+    fn square(k: u128) -> Verdict {
+        Verdict::Square { k: BigUint::from(k) }
+    }
+
+    #[test]
+    fn the_known_solutions_come_back_confirmed() {
+        let broker = BrocardBroker::new(2, 100, 100, Duration::from_secs(1));
+        broker.configure(BrocardSpan::new(2, 100, primes_from(1_000).take(20).collect(), broker.tx.clone())).solve();
+        let report = broker.rx.recv().unwrap();
+
+        assert_eq!(report.verdicts, vec![(4, square(5)), (5, square(11)), (7, square(71))]);
+
+        let written = through_file("verdicts", |path| report.write_to_file(path), |path| std::fs::read_to_string(path));
+
+        assert!(written.starts_with("S:4\nS:5\nS:7\nV:4,5\nV:5,11\nV:7,71\n\n"), "{}", written);
+    }
+
+    #[test]
+    fn a_carried_window_covers_what_one_span_would() {
+        let window = 60;
+        let broker = BrocardBroker::new(2, 200, 7, Duration::from_secs(1)).with_schedule(Schedule::Carried { window });
+        let primes : Vec<u128> = primes_from(1_000).take(20).collect();
+
+        BrocardSpan::new(2, 7, primes.clone(), broker.tx.clone()).solve();
+        let mut report = broker.rx.recv().unwrap();
+        let mut carried = report.candidates.clone();
+        while let Some(handoff) = report.handoff.filter(|h| h.n < broker.window_end(h.n, window)) {
+            let size = broker.resume_size(handoff.n, broker.window_end(handoff.n, window));
+            BrocardSpan::resume(handoff, size, broker.tx.clone()).solve();
+            report = broker.rx.recv().unwrap();
+            carried.extend_from_slice(&report.candidates);
+        }
+
+        BrocardSpan::new(2, window, primes, broker.tx.clone()).solve();
+        assert_eq!(carried, broker.rx.recv().unwrap().candidates);
+    }
+
+    #[test]
+    fn a_failed_chunk_reports_the_rest_of_its_window() {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        BrocardSpan::new(31, 7, vec![1_009, 10], tx).solve();
+        let report = rx.recv().unwrap();
+        assert!(report.error.is_some());
+        assert_eq!(report.range, 31..=38);
+
+        let independent = BrocardBroker::new(2, 200, 7, Duration::from_secs(1));
+        assert_eq!(independent.unsearched(&report), 31..=38);
+        let carried = independent.with_schedule(Schedule::Carried { window: 60 });
+        assert_eq!(carried.unsearched(&report), 31..=62);
+    }
+
+    #[test]
+    fn the_limit_reaches_the_worker() {
+        let broker = BrocardBroker::new(2, 100, 100, Duration::from_secs(1)).with_verify_limit(5);
+        broker.configure(BrocardSpan::new(2, 100, primes_from(1_000).take(20).collect(), broker.tx.clone())).solve();
+
+        assert_eq!(broker.rx.recv().unwrap().verdicts, vec![(4, square(5)), (5, square(11)), (7, Verdict::TooLarge)]);
     }
 }
@@ -14,10 +14,7 @@ pub enum BrocardCandidate {
 impl BrocardCandidate {
     #[inline]
     pub fn is_solution(&self) -> bool {
-        match self {
-            BrocardCandidate::Solution(_) => true,
-            _ => false
-        }
+        matches!(self, BrocardCandidate::Solution(_))
     }
 
     #[inline(always)]
@@ -0,0 +1,42 @@
+use crate::montgomery::residue::{read_residues, write_residues};
+use crate::montgomery::Residue;
+use std::io::{self, BufRead, BufReader, Write};
+
+/// `n!` modulo each of a set of primes; what a `BrocardSpan` leaves behind when it finishes, and
+/// what the next chunk over the same primes can pick up from instead of rebuilding `n!`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Handoff {
+    pub n: u128,
+    pub residues: Vec<Residue>,
+}
+
+impl Handoff {
+    pub fn new(n: u128, residues: Vec<Residue>) -> Handoff {
+        Handoff { n, residues }
+    }
+
+    /// The primes the residues are taken over, in order.
+    pub fn primes(&self) -> Vec<u128> {
+        self.residues.iter().map(|r| r.n).collect()
+    }
+
+    /// Writes `n!` on the first line, then the residues in the format of
+    /// `montgomery::residue::write_to_file`.
+    pub fn write_to_file(&self, filename: &str) -> Result<(), io::Error> {
+        let mut file = std::fs::File::create(filename)?;
+        writeln!(file, "{}!", self.n)?;
+        write_residues(&mut file, &self.residues)
+    }
+
+    pub fn read_from_file(filename: &str) -> Result<Handoff, io::Error> {
+        let mut file = BufReader::new(std::fs::File::open(filename)?);
+
+        let mut header = String::new();
+        file.read_line(&mut header)?;
+        let n = header.trim().strip_suffix('!')
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("not a hand-off header: {:?}", header)))?;
+
+        Ok(Handoff { n, residues: read_residues(file)? })
+    }
+}
@@ -2,5 +2,13 @@ pub mod report;
 pub mod broker;
 pub mod span;
 pub mod candidate;
+pub mod handoff;
+pub mod source;
+pub mod selector;
+pub mod verify;
 
-pub use broker::BrocardBroker;
+pub use broker::{BrocardBroker, Schedule};
+pub use handoff::Handoff;
+pub use source::{NextPrimes, PrimeFile, PrimeSource, RandomPrimes};
+pub use selector::{PrimeSelector, Strategy};
+pub use verify::{verify, Verdict};
@@ -1,4 +1,11 @@
 use crate::brocard::candidate::BrocardCandidate;
+use crate::brocard::handoff::Handoff;
+use crate::brocard::selector::Strategy;
+use crate::brocard::verify::{verify_solutions, Verdict};
+use crate::math::legendre::LegendreSymbol;
+use crate::math::prime::Certificate;
+use crate::montgomery::SpaceError;
+use std::ops::RangeInclusive;
 use std::time::{Duration, Instant};
 use std::io::Write;
 
@@ -8,22 +15,97 @@ pub struct BrocardReport {
     pub primes: Vec<u128>,
     pub start_time: Instant,
     pub duration: Duration,
+    /// The candidates the chunk was given.
+    pub range: RangeInclusive<u128>,
+    /// Set when the chunk couldn't be solved at all, in which case `candidates` is empty.
+    pub error: Option<SpaceError>,
+    /// The candidates that won't be searched because the chunk failed: its own, and for a
+    /// carried window, the rest of the window too. Filled in by the broker.
+    pub unsearched: Option<RangeInclusive<u128>>,
+    /// The factorial of the last candidate in every prime, for the next chunk to carry on from.
+    /// `None` if the chunk failed.
+    pub handoff: Option<Handoff>,
+    /// For each prime, how many of the candidates it was tested on it was a witness (a
+    /// nonresidue) for.
+    pub witnesses: Vec<u64>,
+    /// For each prime, how many candidates it was tested on. Unless the span kept full tallies,
+    /// that's only the candidates no earlier prime had already ruled out.
+    pub tried: Vec<u64>,
+    /// The Legendre symbols actually evaluated.
+    pub tests: u64,
+    /// How the primes were picked, if a `PrimeSelector` picked them.
+    pub strategy: Option<Strategy>,
+    /// A primality certificate for each prime, if the span was asked for them. `None` in the list
+    /// means that prime isn't one.
+    pub certificates: Option<Vec<Option<Certificate>>>,
+    /// What exact arithmetic says about each solution, in order, if the span was asked to check
+    /// them (see `verify`).
+    pub verdicts: Vec<(u128, Verdict)>,
 }
 
 impl BrocardReport {
     pub fn new(primes: Vec<u128>) -> Self {
         BrocardReport {
             candidates: vec![],
+            witnesses: vec![0; primes.len()],
+            tried: vec![0; primes.len()],
+            tests: 0,
+            strategy: None,
+            certificates: None,
+            verdicts: vec![],
             primes,
             start_time: Instant::now(),
             duration: Duration::new(0, 0), // a placeholder
+            range: 0..=0, // a placeholder
+            error: None,
+            unsearched: None,
+            handoff: None,
         }
     }
 
+    /// Marks the chunk as failed, discarding anything already pushed.
+    pub fn fail(&mut self, error: SpaceError) -> &mut Self {
+        self.candidates.clear();
+        self.verdicts.clear();
+        self.handoff = None;
+        self.witnesses.iter_mut().for_each(|w| *w = 0);
+        self.tried.iter_mut().for_each(|t| *t = 0);
+        self.tests = 0;
+        self.error = Some(error);
+        self
+    }
+
     pub fn push(&mut self, candidate: BrocardCandidate) {
         self.candidates.push(candidate);
     }
 
+    /// Counts the Legendre symbols evaluated for a candidate, in prime order, into `witnesses`,
+    /// `tried` and `tests`. `symbols` may stop short of the last prime.
+    pub fn tally(&mut self, symbols: &[LegendreSymbol]) {
+        self.tests += symbols.len() as u64;
+
+        for ((w, t), &s) in self.witnesses.iter_mut().zip(self.tried.iter_mut()).zip(symbols) {
+            *t += 1;
+            if s == LegendreSymbol::Nonresidue { *w += 1; }
+        }
+    }
+
+    /// The mean Legendre symbols evaluated per candidate.
+    pub fn mean_tests(&self) -> f64 {
+        self.tests as f64 / self.candidates.len().max(1) as f64
+    }
+
+    /// Checks every solution up to `limit` exactly, into `verdicts`.
+    pub fn verify(&mut self, limit: u128) -> &mut Self {
+        self.verdicts = verify_solutions(&self.candidates, limit);
+        self
+    }
+
+    pub fn hand_off(&mut self, handoff: Handoff) -> &mut Self {
+        self.handoff = Some(handoff);
+        self
+    }
+
     pub fn finish(&mut self) -> &mut Self {
         self.duration = std::time::Instant::now().duration_since(self.start_time);
         self
@@ -48,13 +130,44 @@ impl BrocardReport {
                 }
             }
         }
+        // The verdict on each solution: the root if `n! + 1` is a square, `-` if it isn't, and
+        // `?` if it was too large to check.
+        for (n, verdict) in &self.verdicts {
+            match verdict {
+                Verdict::Square { k } => writeln!(file, "V:{},{}", n, k)?,
+                Verdict::NotSquare => writeln!(file, "V:{},-", n)?,
+                Verdict::TooLarge => writeln!(file, "V:{},?", n)?,
+            }
+        }
         writeln!(file)?;
 
         writeln!(file, "N:{},M:{},{}", self.candidates.len(), max, max_passed)?; 
+        if let Some(error) = &self.error {
+            writeln!(file, "E:{}", error)?;
+        }
+        if let Some(unsearched) = &self.unsearched {
+            writeln!(file, "U:{},{}", unsearched.start(), unsearched.end())?;
+        }
+        if let Some(strategy) = self.strategy {
+            writeln!(file, "T:{:?},{:.3}", strategy, self.mean_tests())?;
+        }
         write!(file, "P:")?;
         for p in &self.primes {
             write!(file, "{},", p)?;
         }
-        writeln!(file)
+        writeln!(file)?;
+
+        // One certificate per prime, in `Certificate`'s text form, or `-` for none.
+        if let Some(certificates) = &self.certificates {
+            write!(file, "C:")?;
+            for c in certificates {
+                match c {
+                    Some(c) => write!(file, "{};", c)?,
+                    None => write!(file, "-;")?,
+                }
+            }
+            writeln!(file)?;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,191 @@
+use crate::brocard::report::BrocardReport;
+use crate::brocard::source::{NextPrimes, PrimeSource, RandomPrimes};
+use crate::math::prime::primes_from;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// How a `PrimeSelector` picks a chunk's primes. Recorded in every `BrocardReport`, seed and all,
+/// so a run can be repeated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// The first primes above the bound.
+    Consecutive,
+    /// Every `stride`th prime above the bound, so neighbouring primes don't all land in the same
+    /// chunk.
+    Spaced { stride: usize },
+    /// A uniform draw from the `width` integers above the bound; see `RandomPrimes`.
+    SeededRandom { seed: u64, width: u128 },
+    /// The primes among the first `pool` above `above` that have been witnesses most often in
+    /// the reports seen so far, best first. Primes with no history are taken as even odds.
+    ///
+    /// `above` should be the end of the whole search (`start + span`), so that every chunk draws
+    /// from the same pool and what one chunk's report teaches carries over to the next. A chunk
+    /// whose bound is past `above` draws from the primes above its bound instead.
+    EarliestWitness { pool: usize, above: u128 },
+}
+
+/// A `PrimeSource` that picks primes by a `Strategy`, and learns from the reports of the chunks it
+/// picked for when the strategy asks it to.
+///
+/// The measure it works towards is `BrocardReport::mean_tests`: the Legendre symbols a span
+/// evaluates per candidate, which stop at the candidate's first witness. A prime that is a
+/// nonresidue for more of the candidates put to it ends that search sooner, so `EarliestWitness`
+/// puts those first.
+#[derive(Debug)]
+pub struct PrimeSelector {
+    strategy: Strategy,
+    /// For each prime, the candidates it was a witness for and the candidates it was tried on.
+    history: Mutex<HashMap<u128, (u64, u64)>>,
+}
+
+impl PrimeSelector {
+    pub fn new(strategy: Strategy) -> PrimeSelector {
+        PrimeSelector { strategy, history: Mutex::new(HashMap::new()) }
+    }
+
+    /// How often `p` has been a witness, smoothed so an untried prime scores a half.
+    pub fn score(&self, p: u128) -> f64 {
+        let (witnessed, tried) = self.history.lock().unwrap().get(&p).copied().unwrap_or((0, 0));
+        (witnessed as f64 + 1.0) / (tried as f64 + 2.0)
+    }
+}
+
+impl PrimeSource for PrimeSelector {
+    fn primes(&self, bound: u128, count: usize) -> Option<Vec<u128>> {
+        match self.strategy {
+            Strategy::Consecutive => NextPrimes.primes(bound, count),
+            Strategy::Spaced { stride } => {
+                let primes : Vec<u128> = primes_from(bound + 1)
+                    .filter(|&p| p & 1 == 1)
+                    .step_by(stride.max(1))
+                    .take(count)
+                    .collect();
+                (primes.len() == count).then_some(primes)
+            }
+            Strategy::SeededRandom { seed, width } => RandomPrimes::new(seed, width).primes(bound, count),
+            Strategy::EarliestWitness { pool, above } => {
+                let mut primes = NextPrimes.primes(bound.max(above), pool.max(count))?;
+
+                // Highest score first; ties, including every untried prime, stay in order.
+                let scores : HashMap<u128, f64> = primes.iter().map(|&p| (p, self.score(p))).collect();
+                primes.sort_by(|a, b| scores[b].total_cmp(&scores[a]));
+                primes.truncate(count);
+                Some(primes)
+            }
+        }
+    }
+
+    fn strategy(&self) -> Option<Strategy> {
+        Some(self.strategy)
+    }
+
+    fn record(&self, report: &BrocardReport) {
+        if report.error.is_some() { return; }
+
+        // A prime only counts the candidates that got as far as it, so its score is its rate
+        // among those, wherever it stood in the order.
+        let mut history = self.history.lock().unwrap();
+        for ((&p, &witnessed), &tried) in report.primes.iter().zip(&report.witnesses).zip(&report.tried) {
+            let entry = history.entry(p).or_insert((0, 0));
+            entry.0 += witnessed;
+            entry.1 += tried;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brocard::span::BrocardSpan;
+    use crate::math::prime::is_prime;
+
+    #[test]
+    fn every_strategy_gives_odd_primes_above_the_bound() {
+        let strategies = [
+            Strategy::Consecutive,
+            Strategy::Spaced { stride: 3 },
+            Strategy::SeededRandom { seed: 11, width: 10_000 },
+            Strategy::EarliestWitness { pool: 80, above: 2_000 },
+        ];
+
+        for strategy in strategies {
+            let primes = PrimeSelector::new(strategy).primes(1_000, 40).unwrap();
+            assert_eq!(primes.len(), 40, "{:?}", strategy);
+            assert!(primes.iter().all(|&p| p > 1_000 && p & 1 == 1 && is_prime(p)), "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn reports_record_the_strategy() {
+        let strategy = Strategy::SeededRandom { seed: 5, width: 5_000 };
+        let (tx, rx) = crossbeam::channel::unbounded();
+
+        BrocardSpan::from_source(2, 100, &PrimeSelector::new(strategy), 10, tx).unwrap().solve();
+
+        assert_eq!(rx.recv().unwrap().strategy, Some(strategy));
+    }
+
+    #[test]
+    fn spaced_primes_skip_their_neighbours() {
+        let spaced = PrimeSelector::new(Strategy::Spaced { stride: 2 }).primes(100, 3).unwrap();
+        assert_eq!(spaced, vec![101, 107, 113]);
+    }
+
+    #[test]
+    fn earliest_witness_puts_the_best_witnesses_first() {
+        let selector = PrimeSelector::new(Strategy::EarliestWitness { pool: 20, above: 300 });
+        let (tx, rx) = crossbeam::channel::unbounded();
+
+        let primes = selector.primes(300, 20).unwrap();
+        BrocardSpan::new(2, 298, primes.clone(), tx).with_full_tallies(true).solve();
+        let report = rx.recv().unwrap();
+        selector.record(&report);
+
+        let best = primes.iter().zip(&report.witnesses).max_by_key(|&(p, &w)| (w, std::cmp::Reverse(*p))).unwrap().0;
+        let reordered = selector.primes(300, 5).unwrap();
+
+        assert_eq!(reordered[0], *best);
+        assert!(reordered.windows(2).all(|w| selector.score(w[0]) >= selector.score(w[1])));
+    }
+
+    #[test]
+    fn every_chunk_draws_from_the_same_pool() {
+        let selector = PrimeSelector::new(Strategy::EarliestWitness { pool: 30, above: 5_000 });
+        let (tx, rx) = crossbeam::channel::unbounded();
+
+        // Two chunks of a search ending at 5000, far apart, see the same primes.
+        let first = selector.primes(1_000, 30).unwrap();
+        assert_eq!(first, selector.primes(4_000, 30).unwrap());
+
+        BrocardSpan::new(2, 998, first.clone(), tx).with_full_tallies(true).solve();
+        selector.record(&rx.recv().unwrap());
+
+        // So the second chunk's choice is made from the first chunk's history.
+        let second = selector.primes(4_000, 10).unwrap();
+        assert!(second.iter().all(|&p| first.contains(&p) && selector.score(p) != 0.5));
+        assert!(second.windows(2).all(|w| selector.score(w[0]) >= selector.score(w[1])));
+
+        // And a chunk past the pool gets primes above its own bound.
+        assert!(selector.primes(6_000, 10).unwrap().iter().all(|&p| p > 6_000));
+    }
+
+    #[test]
+    fn earliest_witness_on_held_out_candidates() {
+        let selector = PrimeSelector::new(Strategy::EarliestWitness { pool: 80, above: 5_000 });
+        let (tx, rx) = crossbeam::channel::unbounded();
+
+        // Learn from 2..=2000, over the whole pool.
+        BrocardSpan::new(2, 1_998, selector.primes(2_000, 80).unwrap(), tx.clone()).with_full_tallies(true).solve();
+        selector.record(&rx.recv().unwrap());
+
+        // Then run 2001..=4000, which the selector hasn't seen, against its choice of 40 from the
+        // pool and against the first 40 in plain order, each stopping at the first witness.
+        BrocardSpan::new(2_001, 1_999, selector.primes(4_000, 40).unwrap(), tx.clone()).solve();
+        let learned = rx.recv().unwrap();
+        BrocardSpan::new(2_001, 1_999, NextPrimes.primes(5_000, 40).unwrap(), tx).solve();
+        let plain = rx.recv().unwrap();
+
+        assert_eq!(learned.candidates.iter().filter(|c| c.is_solution()).count(), plain.candidates.iter().filter(|c| c.is_solution()).count());
+        assert!(learned.tests < plain.tests, "{} {}", learned.tests, plain.tests);
+    }
+}
@@ -0,0 +1,307 @@
+use crate::brocard::report::BrocardReport;
+use crate::brocard::selector::Strategy;
+use crate::math::prime::{is_prime, primes_from};
+use std::io::{self, BufReader, Read, Write};
+
+/// Where a chunk's primes come from.
+///
+/// A chunk whose candidates end at `bound` needs primes above `bound`, or `bound!` would be zero
+/// in them and tell us nothing. Every source hands back odd primes above `bound`, in the order
+/// they should be tried; ascending, unless the source has learned better.
+pub trait PrimeSource: Send + Sync {
+    /// `count` primes above `bound`, or `None` if this source can't supply that many.
+    fn primes(&self, bound: u128, count: usize) -> Option<Vec<u128>>;
+
+    /// The `Strategy` this source amounts to, for the reports to record.
+    fn strategy(&self) -> Option<Strategy> {
+        None
+    }
+
+    /// Sees the report of a chunk run over this source's primes.
+    fn record(&self, _report: &BrocardReport) {}
+}
+
+/// The first primes above the bound; what the broker has always used.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NextPrimes;
+
+impl PrimeSource for NextPrimes {
+    fn primes(&self, bound: u128, count: usize) -> Option<Vec<u128>> {
+        let primes : Vec<u128> = primes_from(bound + 1).filter(|&p| p & 1 == 1).take(count).collect();
+        (primes.len() == count).then_some(primes)
+    }
+
+    fn strategy(&self) -> Option<Strategy> {
+        Some(Strategy::Consecutive)
+    }
+}
+
+/// The first primes above the bound from a fixed list, so a run can be repeated over exactly the
+/// primes an earlier one used.
+///
+/// Two file formats are read. The text format is the one `primes.txt` is in: decimal primes
+/// separated by commas (and any whitespace). The binary format starts with `BINARY_MAGIC`, then
+/// holds LEB128 varints: the number of primes, the first prime, and then half of each gap to the
+/// next, which keeps a list of consecutive primes to a byte or two per prime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrimeFile {
+    primes: Vec<u128>,
+}
+
+/// The first bytes of a binary prime file.
+pub const BINARY_MAGIC: &[u8; 4] = b"BPD\x01";
+
+impl PrimeFile {
+    /// Takes a list of odd primes in ascending order, or says why it isn't one.
+    pub fn new(primes: Vec<u128>) -> Result<PrimeFile, io::Error> {
+        if let Some(w) = primes.windows(2).find(|w| w[0] >= w[1]) {
+            return Err(invalid(format!("primes out of order: {} then {}", w[0], w[1])));
+        }
+        if let Some(p) = primes.iter().find(|&&p| p & 1 == 0 || !is_prime(p)) {
+            return Err(invalid(format!("not an odd prime: {}", p)));
+        }
+
+        Ok(PrimeFile { primes })
+    }
+
+    pub fn primes_in_file(&self) -> &[u128] {
+        &self.primes
+    }
+
+    pub fn write_text(&self, out: &mut impl Write) -> Result<(), io::Error> {
+        let line : Vec<String> = self.primes.iter().map(|p| p.to_string()).collect();
+        writeln!(out, "{}", line.join(","))
+    }
+
+    pub fn write_binary(&self, out: &mut impl Write) -> Result<(), io::Error> {
+        out.write_all(BINARY_MAGIC)?;
+        write_varint(out, self.primes.len() as u128)?;
+
+        let mut last = None;
+        for &p in self.primes.iter() {
+            match last {
+                None => write_varint(out, p)?,
+                Some(q) => write_varint(out, (p - q) / 2)?,
+            }
+            last = Some(p);
+        }
+        Ok(())
+    }
+
+    /// Reads either format, telling them apart by `BINARY_MAGIC`.
+    pub fn read(input: impl Read) -> Result<PrimeFile, io::Error> {
+        let mut bytes = vec![];
+        BufReader::new(input).read_to_end(&mut bytes)?;
+
+        let primes = match bytes.strip_prefix(BINARY_MAGIC) {
+            Some(mut body) => {
+                let count = read_varint(&mut body)?;
+                let mut primes = Vec::with_capacity(count.min(1 << 20) as usize);
+                for i in 0..count {
+                    let v = read_varint(&mut body)?;
+                    let p = if i == 0 { v } else {
+                        v.checked_mul(2).and_then(|gap| gap.checked_add(primes[i as usize - 1]))
+                            .ok_or_else(|| invalid("prime gap overflows".to_string()))?
+                    };
+                    primes.push(p);
+                }
+                primes
+            }
+            None => {
+                let text = String::from_utf8(bytes).map_err(|e| invalid(e.to_string()))?;
+                text.split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.parse().map_err(|e| invalid(format!("{:?}: {}", s, e))))
+                    .collect::<Result<Vec<u128>, io::Error>>()?
+            }
+        };
+
+        PrimeFile::new(primes)
+    }
+
+    pub fn write_text_file(&self, filename: &str) -> Result<(), io::Error> {
+        let mut file = std::fs::File::create(filename)?;
+        self.write_text(&mut file)
+    }
+
+    pub fn write_binary_file(&self, filename: &str) -> Result<(), io::Error> {
+        let mut file = std::fs::File::create(filename)?;
+        self.write_binary(&mut file)
+    }
+
+    pub fn read_from_file(filename: &str) -> Result<PrimeFile, io::Error> {
+        PrimeFile::read(std::fs::File::open(filename)?)
+    }
+}
+
+impl PrimeSource for PrimeFile {
+    fn primes(&self, bound: u128, count: usize) -> Option<Vec<u128>> {
+        let from = self.primes.partition_point(|&p| p <= bound);
+        self.primes.get(from..from + count).map(|primes| primes.to_vec())
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_varint(out: &mut impl Write, mut v: u128) -> Result<(), io::Error> {
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 { return out.write_all(&[byte]); }
+        out.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(input: &mut &[u8]) -> Result<u128, io::Error> {
+    let mut v = 0u128;
+    for shift in (0..u128::BITS).step_by(7) {
+        let (&byte, rest) = input.split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "truncated prime file"))?;
+        *input = rest;
+
+        let bits = (byte & 0x7f) as u128;
+        if shift > u128::BITS - 7 && bits >> (u128::BITS - shift) != 0 {
+            return Err(invalid("varint overflows u128".to_string()));
+        }
+        v |= bits << shift;
+        if byte & 0x80 == 0 { return Ok(v); }
+    }
+    Err(invalid("varint overflows u128".to_string()))
+}
+
+/// `count` primes drawn at random from the `width` integers above the bound. The draw depends
+/// only on `seed` and the bound, so a run with the same seed gets the same primes for every
+/// chunk.
+#[derive(Debug, Clone, Copy)]
+pub struct RandomPrimes {
+    pub seed: u64,
+    pub width: u128,
+}
+
+impl RandomPrimes {
+    pub fn new(seed: u64, width: u128) -> RandomPrimes {
+        RandomPrimes { seed, width }
+    }
+}
+
+impl PrimeSource for RandomPrimes {
+    fn primes(&self, bound: u128, count: usize) -> Option<Vec<u128>> {
+        let mut pool : Vec<u128> = primes_from(bound + 1)
+            .take_while(|&p| p <= bound.saturating_add(self.width))
+            .filter(|&p| p & 1 == 1)
+            .collect();
+        if pool.len() < count { return None; }
+
+        // A partial Fisher-Yates shuffle puts a uniform choice of `count` primes at the front.
+        let mut rng = SplitMix64::new(self.seed ^ (bound as u64) ^ ((bound >> 64) as u64).rotate_left(32));
+        for i in 0..count {
+            let j = i + rng.below((pool.len() - i) as u64) as usize;
+            pool.swap(i, j);
+        }
+
+        let mut primes = pool[..count].to_vec();
+        primes.sort_unstable();
+        Some(primes)
+    }
+
+    fn strategy(&self) -> Option<Strategy> {
+        Some(Strategy::SeededRandom { seed: self.seed, width: self.width })
+    }
+}
+
+/// A small, seedable generator (Steele, Lea & Flood's SplitMix64), so seeded runs don't depend on
+/// an outside crate's choice of algorithm.
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`, by rejecting the draws that would bias the remainder.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone { return x % n; }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_shipped_primes_txt() {
+        let file = PrimeFile::read_from_file("primes.txt").unwrap();
+        let expected : Vec<u128> = primes_from(1_000_000_000).take(file.primes_in_file().len()).collect();
+
+        assert_eq!(file.primes_in_file()[0], 1_000_000_007);
+        assert_eq!(file.primes_in_file(), expected);
+    }
+
+    #[quickcheck]
+    fn both_formats_round_trip(start: u64, count: u8) -> bool {
+        let file = PrimeFile::new(primes_from(start as u128 | 1).filter(|&p| p & 1 == 1).take(count as usize).collect()).unwrap();
+
+        let (mut text, mut binary) = (vec![], vec![]);
+        file.write_text(&mut text).unwrap();
+        file.write_binary(&mut binary).unwrap();
+
+        PrimeFile::read(&text[..]).unwrap() == file && PrimeFile::read(&binary[..]).unwrap() == file
+    }
+
+    #[test]
+    fn rejects_lists_that_are_not_ascending_odd_primes() {
+        for text in ["7,5,11", "7,9,11", "2,3,5", "7,,x"] {
+            assert!(PrimeFile::read(text.as_bytes()).is_err(), "{}", text);
+        }
+        assert!(PrimeFile::read(&BINARY_MAGIC[..]).is_err());
+    }
+
+    #[test]
+    fn every_source_gives_odd_primes_above_the_bound() {
+        let file = PrimeFile::new(primes_from(3).take(2_000).collect()).unwrap();
+        let sources : [&dyn PrimeSource; 3] = [&NextPrimes, &file, &RandomPrimes::new(7, 10_000)];
+
+        for source in sources {
+            for bound in [2, 1_000, 5_000] {
+                let primes = source.primes(bound, 40).unwrap();
+                assert_eq!(primes.len(), 40);
+                assert!(primes.windows(2).all(|w| w[0] < w[1]));
+                assert!(primes.iter().all(|&p| p > bound && p & 1 == 1 && is_prime(p)));
+            }
+        }
+    }
+
+    #[test]
+    fn file_and_random_sources_can_run_out() {
+        let file = PrimeFile::new(primes_from(3).take(10).collect()).unwrap();
+        assert_eq!(file.primes(20, 5), None);
+        assert_eq!(RandomPrimes::new(0, 100).primes(1_000_000, 50), None);
+    }
+
+    #[test]
+    fn random_primes_depend_only_on_seed_and_bound() {
+        let (a, b) = (RandomPrimes::new(1, 100_000), RandomPrimes::new(2, 100_000));
+
+        assert_eq!(a.primes(1_000, 30), a.primes(1_000, 30));
+        assert_ne!(a.primes(1_000, 30), b.primes(1_000, 30));
+        assert_ne!(a.primes(1_000, 30), a.primes(2_000, 30));
+    }
+}
@@ -2,24 +2,73 @@ use crate::montgomery::*;
 use crate::math::legendre::*;
 use crate::brocard::report::*;
 use crate::brocard::candidate::*;
+use crate::brocard::handoff::Handoff;
+use crate::brocard::selector::Strategy;
+use crate::math::prime::{certify, Certificate};
+use crate::brocard::source::PrimeSource;
+use crate::ring::{BarrettSpace, ModRing, NaiveSpace};
 use crossbeam::channel::Sender;
 
-/// Tests all the values within [start,start+span] against the given prime and reports back any
+/// How a `BrocardSpan` holds its residues while it walks the candidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// One `Elt` per prime, each pointing at its own `Space`.
+    #[default]
+    Elementwise,
+    /// A single `SpaceBatch` holding every prime's constants and residue in contiguous arrays.
+    Batched,
+}
+
+/// How a `BrocardSpan` evaluates the legendre symbol of each `n! + 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymbolMethod {
+    /// Euler's criterion, `a^((p-1)/2)`, computed inside the Montgomery space.
+    #[default]
+    Euler,
+    /// The binary Jacobi algorithm on the value after exiting the space.
+    Jacobi,
+}
+
+/// Which modular reduction a `BrocardSpan` does its arithmetic with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// `montgomery::Space`, in the narrowest word that holds the primes.
+    #[default]
+    Montgomery,
+    /// `ring::BarrettSpace`. Only takes primes below 2^64.
+    Barrett,
+    /// `ring::NaiveSpace`, built on `math::mod_mult`. A slow reference point.
+    Naive,
+}
+
+/// Tests all the values within [start,start+span] against the given primes and reports back any
 /// successes
 ///
-/// FIXME: this will mean that even if I find a witness to non-solution, I will still have to
-/// test against every prime in my list, that's not ideal. This should take an array of primes
-/// instead, and then return only those that fail for every prime in the range.
-///
+/// Each candidate is tested against the primes in order and dropped at its first witness, so the
+/// order the primes come in decides how many Legendre symbols the span costs (see
+/// `PrimeSelector`). `with_full_tallies` tests every candidate against every prime instead.
 pub struct BrocardSpan {
     start: u128,
     span: u128,
     primes: Vec<u128>,
     tx: Sender<BrocardReport>, // probably need to put a lock around this?
+    layout: Layout,
+    method: SymbolMethod,
+    backend: Backend,
+    /// `(start - 1)!` in each prime, if an earlier chunk already worked it out.
+    initial: Option<Vec<Residue>>,
+    /// How the primes were picked, passed through to the report.
+    strategy: Option<Strategy>,
+    /// Whether the report carries a primality certificate for each prime.
+    certify: bool,
+    /// Certificates an earlier chunk over the same primes already worked out.
+    known_certificates: Option<Vec<Option<Certificate>>>,
+    /// Solutions up to this are checked exactly before the report goes back.
+    verify_limit: Option<u128>,
+    /// Whether every candidate is tested against every prime, past its first witness.
+    full_tallies: bool,
 }
 
-const R_EXP : usize = 64;
-
 impl BrocardSpan {
     // TODO: have it automatically calculate the primes it needs? Or maybe wrap this in another
     // object which creates and manages spans and does that? not sure.
@@ -28,43 +77,182 @@ impl BrocardSpan {
             start,
             span,
             primes,
-            tx
+            tx,
+            layout: Layout::default(),
+            method: SymbolMethod::default(),
+            backend: Backend::default(),
+            initial: None,
+            strategy: None,
+            certify: false,
+            known_certificates: None,
+            verify_limit: None,
+            full_tallies: false,
         }
     }
 
+    /// A span over `[start, start + span]`, testing against `count` primes from `source` above
+    /// the last candidate. `None` if the source can't supply them.
+    pub fn from_source(start: u128, span: u128, source: &dyn PrimeSource, count: usize, tx: Sender<BrocardReport>) -> Option<BrocardSpan> {
+        source.primes(start + span, count).map(|primes| BrocardSpan::new(start, span, primes, tx).with_strategy(source.strategy()))
+    }
+
+    /// A span that carries on from where an earlier one over the same primes left off, without
+    /// rebuilding `handoff.n!`. The earlier span already tested `handoff.n` itself, so this one
+    /// starts just past it, and `span` means what it does to `new`: the span covers
+    /// `[handoff.n + 1, handoff.n + 1 + span]`.
+    pub fn resume(handoff: Handoff, span: u128, tx: Sender<BrocardReport>) -> BrocardSpan {
+        let mut s = BrocardSpan::new(handoff.n + 1, span, handoff.primes(), tx);
+        s.initial = Some(handoff.residues);
+        s
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> BrocardSpan {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_symbol_method(mut self, method: SymbolMethod) -> BrocardSpan {
+        self.method = method;
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: Option<Strategy>) -> BrocardSpan {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Has the report carry a certificate for every prime (see `math::prime::certify`), so the
+    /// witnesses it names can be checked without trusting `is_prime`.
+    pub fn with_certificates(mut self, certify: bool) -> BrocardSpan {
+        self.certify = certify;
+        self
+    }
+
+    /// Certificates for this span's primes from an earlier report over them, such as the one a
+    /// carried window's last chunk sent back, so certifying chunks don't redo the work, Pratt
+    /// factorizations and all, for every chunk of a window. Ignored unless there's one per prime.
+    pub fn with_known_certificates(mut self, certificates: Option<Vec<Option<Certificate>>>) -> BrocardSpan {
+        self.known_certificates = certificates.filter(|c| c.len() == self.primes.len());
+        self
+    }
+
+    /// Has the worker check each solution up to `limit` with exact arithmetic (see `verify`) and
+    /// put the verdicts in the report, so whoever receives it doesn't have to.
+    pub fn with_verify_limit(mut self, limit: u128) -> BrocardSpan {
+        self.verify_limit = Some(limit);
+        self
+    }
+
+    /// Tests every candidate against every prime rather than stopping at its first witness, so the
+    /// report's `witnesses` count, for each prime, every candidate it rules out, and `tried` is
+    /// the whole span for all of them. That's a Legendre symbol per prime per candidate.
+    pub fn with_full_tallies(mut self, full: bool) -> BrocardSpan {
+        self.full_tallies = full;
+        self
+    }
+
+    fn new_report(&self) -> BrocardReport {
+        let mut report = BrocardReport::new(self.primes.clone());
+        report.range = self.start..=self.start + self.span;
+        report.strategy = self.strategy;
+        if self.certify {
+            report.certificates = Some(match &self.known_certificates {
+                Some(known) => known.clone(),
+                None => self.primes.iter().map(|&p| certify(p)).collect(),
+            });
+        }
+        report
+    }
+
+    /// Only the Montgomery backend has a batched layout; the others always solve elementwise.
+    pub fn with_backend(mut self, backend: Backend) -> BrocardSpan {
+        self.backend = backend;
+        self
+    }
+
+    /// Solves the span with the configured backend, sending the report down `tx`.
     pub fn solve(&self) {
-        let mut result = BrocardReport::new(self.primes.clone());
+        match self.backend {
+            Backend::Montgomery => self.solve_montgomery(),
+            Backend::Barrett => self.solve_in::<BarrettSpace>(),
+            Backend::Naive => self.solve_in::<NaiveSpace>(),
+        }
+    }
+
+    /// Picks the narrowest word that holds every prime and solves in it. A u64 space does a
+    /// single native multiply per step where a u128 space needs four, so there's no reason to pay
+    /// for the wider word until the primes demand it.
+    fn solve_montgomery(&self) {
+        let largest = self.primes.iter().copied().max().unwrap_or(0);
 
-        // 1. line up all the primes and build montgomery spaces around them
-        let spaces : Vec<Space<R_EXP>> = self.primes.iter().map(|p| Space::new(*p)).collect();
+        if largest <= u32::MAX as u128 {
+            self.solve_with::<32, u32>()
+        } else if largest <= u64::MAX as u128 {
+            self.solve_with::<64, u64>()
+        } else {
+            self.solve_with::<128, u128>()
+        }
+    }
+
+    fn solve_with<const R_EXP: usize, W: Word>(&self) {
+        match self.layout {
+            Layout::Elementwise => self.solve_in::<Space<R_EXP, W>>(),
+            Layout::Batched => self.solve_batched_in::<R_EXP, W>(),
+        }
+    }
+
+    /// Solves the span one prime at a time, in whichever `ModRing` is asked for.
+    pub fn solve_in<R: ModRing>(&self) {
+        let mut result = self.new_report();
+
+        // 1. line up all the primes and build a ring around each. A bad prime list is reported
+        //    back rather than panicking the worker.
+        let rings : Vec<R> = match self.primes.iter().map(|&p| R::try_new(p)).collect() {
+            Ok(rings) => rings,
+            Err(e) => {
+                let _ = self.tx.send(result.fail(e).finish().clone());
+                return;
+            }
+        };
         // 2. identify our first candidate to try
         let mut candidate = self.start;
         // 3. create an initial vector of elts V_i = p_i.factorial(candidate) that all represent
-        //    the factorial of the current candidate in each montgomery space 
-        let mut v : Vec<Elt<R_EXP>> = spaces.iter().map(|s| s.factorial(candidate)).collect();
+        //    the factorial of the current candidate in each montgomery space. The primes sit just
+        //    above the span, so this is usually done by Wilson reflection, and costs about as much
+        //    as the span is long rather than as far as `start` is from zero. If we were handed
+        //    them by the previous chunk, there's nothing to do at all.
+        //    The candidate itself is entered once and stepped by adding one, so the walk never
+        //    divides.
+        let ones : Vec<R::Elt> = rings.iter().map(|ring| ring.enter(1)).collect();
+        let mut c : Vec<R::Elt> = rings.iter().map(|ring| ring.enter(candidate)).collect();
+        let mut v : Vec<R::Elt> = match &self.initial {
+            // `resume` takes the primes from the residues, so they always line up. They hold the
+            // earlier chunk's last factorial, one short of ours.
+            Some(residues) => rings.iter().zip(residues).zip(&c).map(|((ring, r), &c_i)| ring.mul(ring.enter(r.val), c_i)).collect(),
+            None => rings.iter().map(|ring| ring.prime_factorial(candidate)).collect(),
+        };
+        let mut symbols = vec![LegendreSymbol::Divisor; rings.len()];
 
 
         loop {
             // 4.1. calculate the value of the legendre symbol `V_i R p_i`, filtering for 
-            //      NSWs
-            // TODO: 2. It would be nice to get the count of how many passed, but not critical
+            //      NSWs, up to the first witness
             // TODO: 3. This is a little ugly, maybe wrapping up the Primes in it's own object
             // would make it nicer?
-            let mut test = v.iter().map(|v_i| { (*v_i + 1).legendre() });
-
-            if test.any(|s| s == LegendreSymbol::Nonresidue) {
-                // 4.2.1 if all the legendre symbols are residues, add the candidate to the list of
-                //       potential solutions
-                result.push(BrocardCandidate::Nonsolution { 
-                    candidate,
-                    passed: test.filter(|&s| s == LegendreSymbol::Residue).count() 
-                });
-            } else {
-                // 4.2.2 if any of the legendre symbols are non-residues, add the candidate to the
-                //       list of non-solutions
-                result.push(BrocardCandidate::Solution(candidate));
+            let mut evaluated = 0;
+            for (((ring, &v_i), &one), symbol) in rings.iter().zip(&v).zip(&ones).zip(symbols.iter_mut()) {
+                let a = ring.add(v_i, one);
+                *symbol = match self.method {
+                    SymbolMethod::Euler => ring.legendre(a),
+                    SymbolMethod::Jacobi => ring.jacobi(a),
+                };
+                evaluated += 1;
+                if *symbol == LegendreSymbol::Nonresidue && !self.full_tallies { break; }
             }
 
+            result.tally(&symbols[..evaluated]);
+            result.push(classify(candidate, &symbols[..evaluated]));
+
             // 4.3. increment the candidate by one, 
             candidate += 1;
 
@@ -73,16 +261,305 @@ impl BrocardSpan {
                 break;
             }
 
-            // 4.5. multiply `V_i * p.enter(candidate)` for all i. This set's V_i = (i+1)!
-            v.iter_mut().for_each(|v_i| {
-                *v_i *= candidate
-            });
+            // 4.5. step `C_i` on to the new candidate and multiply `V_i * C_i` for all i. This
+            //      set's V_i = (i+1)!
+            for (((ring, v_i), c_i), &one) in rings.iter().zip(v.iter_mut()).zip(c.iter_mut()).zip(&ones) {
+                *c_i = ring.add(*c_i, one);
+                *v_i = ring.mul(*v_i, *c_i);
+            }
         }
         // 5. return the list of candidates that passed the test. Additionally return metadata
-        //    about time spent, etc, for optimization
-        let ret = result.finish();
+        //    about time spent, etc, for optimization, and the last factorial so the next chunk
+        //    can pick up from it.
+        let end = Handoff::new(
+            self.start + self.span,
+            rings.iter().zip(&v).map(|(ring, &v_i)| Residue::new(ring.exit(v_i), ring.modulus())).collect()
+        );
+        if let Some(limit) = self.verify_limit {
+            result.verify(limit);
+        }
+        let ret = result.hand_off(end).finish();
         // FIXME: This is probably wrong. I should do some kind of error handling/requeing.
         let _ = self.tx.send(ret.clone());
     }
 
+    /// The same walk as `solve_in`, but with the residues held in a `SpaceBatch`, so each step is
+    /// a handful of straight passes over contiguous arrays instead of a pass over `Elt`s.
+    pub fn solve_batched_in<const R_EXP: usize, W: Word>(&self) {
+        let mut result = self.new_report();
+
+        let mut batch = match SpaceBatch::<R_EXP, W>::try_new(&self.primes) {
+            Ok(batch) => batch,
+            Err(e) => {
+                let _ = self.tx.send(result.fail(e).finish().clone());
+                return;
+            }
+        };
+        let mut symbols = vec![LegendreSymbol::Divisor; batch.len()];
+
+        let mut candidate = self.start;
+        match &self.initial {
+            // The earlier chunk's last factorial is one short of ours, as in `solve_in`.
+            Some(residues) => {
+                batch.set_residues(residues);
+                batch.set_counter(candidate - 1);
+                batch.advance();
+            }
+            None => {
+                batch.set_prime_factorial(candidate);
+                batch.set_counter(candidate);
+            }
+        }
+
+        loop {
+            // Test `V_i + 1` lane by lane up to the first witness, then put `V_i` back the way we
+            // found it.
+            batch.add_one();
+            let mut evaluated = 0;
+            for (i, symbol) in symbols.iter_mut().enumerate() {
+                *symbol = match self.method {
+                    SymbolMethod::Euler => batch.legendre(i),
+                    SymbolMethod::Jacobi => batch.jacobi(i),
+                };
+                evaluated += 1;
+                if *symbol == LegendreSymbol::Nonresidue && !self.full_tallies { break; }
+            }
+            batch.sub_one();
+
+            result.tally(&symbols[..evaluated]);
+            result.push(classify(candidate, &symbols[..evaluated]));
+
+            candidate += 1;
+
+            if candidate > self.start + self.span {
+                break;
+            }
+
+            batch.advance();
+        }
+
+        let end = Handoff::new(self.start + self.span, batch.to_residues());
+        if let Some(limit) = self.verify_limit {
+            result.verify(limit);
+        }
+        let ret = result.hand_off(end).finish();
+        let _ = self.tx.send(ret.clone());
+    }
+}
+
+/// Sorts a candidate into a solution or nonsolution from the legendre symbols evaluated for it,
+/// in prime order. A nonsolution has `passed` the primes it was a residue for before its first
+/// witness, which is all of them that are certain to have been tested.
+fn classify(candidate: u128, symbols: &[LegendreSymbol]) -> BrocardCandidate {
+    if let Some(first) = symbols.iter().position(|&s| s == LegendreSymbol::Nonresidue) {
+        // 4.2.1 if any of the legendre symbols are non-residues, add the candidate to the
+        //       list of non-solutions
+        BrocardCandidate::Nonsolution {
+            candidate,
+            passed: symbols[..first].iter().filter(|&&s| s == LegendreSymbol::Residue).count()
+        }
+    } else {
+        // 4.2.2 if all the legendre symbols are residues, add the candidate to the list of
+        //       potential solutions
+        BrocardCandidate::Solution(candidate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::prime::primes_from;
+    use crate::montgomery::test_case::through_file;
+
+    fn run(layout: Layout, method: SymbolMethod) -> Vec<BrocardCandidate> {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let primes = primes_from(1_000).take(20).collect();
+
+        BrocardSpan::new(2, 100, primes, tx).with_layout(layout).with_symbol_method(method).solve();
+
+        rx.recv().unwrap().candidates
+    }
+
+    #[test]
+    fn batched_layout_matches_elementwise() {
+        assert_eq!(run(Layout::Batched, SymbolMethod::Euler), run(Layout::Elementwise, SymbolMethod::Euler));
+    }
+
+    #[test]
+    fn jacobi_matches_euler() {
+        assert_eq!(run(Layout::Elementwise, SymbolMethod::Jacobi), run(Layout::Elementwise, SymbolMethod::Euler));
+        assert_eq!(run(Layout::Batched, SymbolMethod::Jacobi), run(Layout::Batched, SymbolMethod::Euler));
+    }
+
+    #[test]
+    fn candidates_stop_at_their_first_witness() {
+        for layout in [Layout::Elementwise, Layout::Batched] {
+            let (tx, rx) = crossbeam::channel::unbounded();
+            let primes : Vec<u128> = primes_from(1_000).take(20).collect();
+
+            BrocardSpan::new(2, 100, primes.clone(), tx.clone()).with_layout(layout).solve();
+            let early = rx.recv().unwrap();
+            BrocardSpan::new(2, 100, primes, tx).with_layout(layout).with_full_tallies(true).solve();
+            let full = rx.recv().unwrap();
+
+            // The same verdicts either way, for a fraction of the symbols.
+            assert_eq!(early.candidates, full.candidates);
+            assert_eq!(full.tests, 101 * 20);
+            assert!(early.tests < full.tests / 4, "{}", early.tests);
+            assert_eq!(early.tests, early.tried.iter().sum::<u64>());
+
+            // Every candidate meets the first prime, and the ones it rules out go no further.
+            assert_eq!((early.tried[0], early.witnesses[0]), (full.tried[0], full.witnesses[0]));
+            assert_eq!(early.tried[1], early.tried[0] - early.witnesses[0]);
+            assert!(full.tried.iter().all(|&t| t == 101));
+        }
+    }
+
+    #[test]
+    fn bad_primes_are_reported() {
+        for layout in [Layout::Elementwise, Layout::Batched] {
+            let (tx, rx) = crossbeam::channel::unbounded();
+
+            BrocardSpan::new(2, 100, vec![1009, 1012, 1013], tx).with_layout(layout).solve();
+
+            let report = rx.recv().unwrap();
+            assert_eq!(report.error, Some(SpaceError::EvenModulus(1012)));
+            assert!(report.candidates.is_empty());
+        }
+    }
+
+    #[test]
+    fn chunk_setup_is_independent_of_start() {
+        // Building 10^12! forwards would take the better part of an hour per prime.
+        let start = 1_000_000_000_000;
+        let primes : Vec<u128> = primes_from(start + 11).take(5).collect();
+
+        for layout in [Layout::Elementwise, Layout::Batched] {
+            let (tx, rx) = crossbeam::channel::unbounded();
+            BrocardSpan::new(start, 10, primes.clone(), tx).with_layout(layout).solve();
+
+            assert_eq!(rx.recv().unwrap().candidates.len(), 11);
+        }
+    }
+
+    #[test]
+    fn resumed_chunks_match_a_single_chunk() {
+        for layout in [Layout::Elementwise, Layout::Batched] {
+            let (tx, rx) = crossbeam::channel::unbounded();
+            let primes : Vec<u128> = primes_from(1_000).take(20).collect();
+
+            BrocardSpan::new(2, 50, primes.clone(), tx.clone()).with_layout(layout).solve();
+            let first = rx.recv().unwrap();
+            let handoff = first.handoff.clone().unwrap();
+            assert_eq!(handoff.n, 52);
+            assert_eq!(handoff.primes(), primes);
+
+            BrocardSpan::resume(handoff, 49, tx).with_layout(layout).solve();
+            let second = rx.recv().unwrap();
+
+            // The second chunk picks up at 53, so between them every candidate is tested once.
+            assert_eq!(second.candidates.len(), 50);
+            assert_eq!(second.handoff.as_ref().unwrap().n, 102);
+
+            let mut carried = first.candidates;
+            carried.extend_from_slice(&second.candidates);
+
+            assert_eq!(carried, run(layout, SymbolMethod::Euler));
+        }
+    }
+
+    #[test]
+    fn handoff_round_trips_through_file() {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        BrocardSpan::new(2, 30, primes_from(1_000).take(5).collect(), tx).solve();
+        let handoff = rx.recv().unwrap().handoff.unwrap();
+
+        let restored = through_file("handoff", |path| handoff.write_to_file(path), Handoff::read_from_file);
+
+        assert_eq!(restored, handoff);
+    }
+
+    #[test]
+    fn every_backend_matches_montgomery() {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let primes : Vec<u128> = primes_from(1_000).take(20).collect();
+
+        for backend in [Backend::Barrett, Backend::Naive] {
+            BrocardSpan::new(2, 100, primes.clone(), tx.clone()).with_backend(backend).solve();
+            let report = rx.recv().unwrap();
+
+            assert_eq!(report.candidates, run(Layout::Elementwise, SymbolMethod::Euler), "{:?}", backend);
+        }
+    }
+
+    #[test]
+    fn barrett_reports_wide_primes() {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let n = (1 << 64) + 13;
+
+        BrocardSpan::new(2, 10, vec![1009, n], tx).with_backend(Backend::Barrett).solve();
+
+        assert_eq!(rx.recv().unwrap().error, Some(SpaceError::ModulusTooLarge { n, r_exp: 64 }));
+    }
+
+    #[test]
+    fn finds_known_solutions() {
+        let solutions : Vec<u128> = run(Layout::Batched, SymbolMethod::Euler).iter().filter_map(|c| match c {
+            BrocardCandidate::Solution(n) => Some(*n),
+            _ => None
+        }).collect();
+
+        assert_eq!(solutions, vec![4, 5, 7]);
+    }
+
+    #[test]
+    fn spans_from_a_prime_file_use_exactly_its_primes() {
+        use crate::brocard::source::PrimeFile;
+
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let file = PrimeFile::new(primes_from(101).filter(|&p| p != 107).take(20).collect()).unwrap();
+
+        BrocardSpan::from_source(2, 98, &file, 20, tx.clone()).unwrap().solve();
+        let report = rx.recv().unwrap();
+
+        assert_eq!(report.primes, file.primes_in_file());
+        assert!(BrocardSpan::from_source(2, 98, &file, 21, tx).is_none());
+    }
+
+    #[test]
+    fn reports_carry_certificates_when_asked() {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let primes : Vec<u128> = primes_from(1_000_000_000).take(5).collect();
+
+        BrocardSpan::new(2, 100, primes.clone(), tx.clone()).solve();
+        assert_eq!(rx.recv().unwrap().certificates, None);
+
+        BrocardSpan::new(2, 100, primes.clone(), tx).with_certificates(true).solve();
+        let certificates = rx.recv().unwrap().certificates.unwrap();
+
+        assert!(certificates.iter().zip(&primes).all(|(c, &p)| c.as_ref().is_some_and(|c| c.prime() == p && c.verify())));
+    }
+
+    #[test]
+    fn resumed_chunks_reuse_known_certificates() {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let primes : Vec<u128> = primes_from(1_000_000_000).take(5).collect();
+
+        BrocardSpan::new(2, 50, primes, tx.clone()).with_certificates(true).solve();
+        let first = rx.recv().unwrap();
+
+        // Stand-ins no real certification would give back, to show they weren't recomputed.
+        let stand_ins = vec![None; 5];
+        BrocardSpan::resume(first.handoff.clone().unwrap(), 49, tx.clone())
+            .with_certificates(true)
+            .with_known_certificates(Some(stand_ins.clone()))
+            .solve();
+        assert_eq!(rx.recv().unwrap().certificates, Some(stand_ins));
+
+        BrocardSpan::resume(first.handoff.clone().unwrap(), 49, tx)
+            .with_certificates(true)
+            .with_known_certificates(first.certificates.clone())
+            .solve();
+        assert_eq!(rx.recv().unwrap().certificates, first.certificates);
+    }
 }
@@ -0,0 +1,108 @@
+use crate::brocard::candidate::BrocardCandidate;
+use crate::math::bigint::BigUint;
+use std::fmt;
+
+/// Past this, `n! + 1` is too big to work out and take the square root of here. At the limit it
+/// has about 700,000 bits, and the schoolbook arithmetic in `BigUint` takes a second and a half
+/// over it in a release build; the cost grows with the square of `n`.
+pub const VERIFY_LIMIT: u128 = 50_000;
+
+/// What the exact arithmetic says about a candidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// `n! + 1 = k^2`.
+    Square { k: BigUint },
+    /// `n! + 1` isn't a square; every prime the candidate was tested against happened to have it
+    /// as a residue.
+    NotSquare,
+    /// `n` is past the limit it was checked against, so nothing was computed.
+    TooLarge,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Square { k } => write!(f, "the square of {}", k),
+            Verdict::NotSquare => write!(f, "not a square"),
+            Verdict::TooLarge => write!(f, "too large to check exactly"),
+        }
+    }
+}
+
+/// Decides exactly whether `n! + 1` is a square, for `n` up to `VERIFY_LIMIT`.
+///
+/// The Legendre symbols only ever rule candidates out, so a `BrocardCandidate::Solution` means no
+/// more than that none of the chunk's primes did. This computes `n!` outright, with
+/// `BigUint::factorial`'s product tree, and takes the square root of `n! + 1`.
+pub fn verify(n: u128) -> Verdict {
+    verify_up_to(n, VERIFY_LIMIT)
+}
+
+/// `verify`, with a guard of `limit` rather than `VERIFY_LIMIT`. The guard can only be lowered.
+pub fn verify_up_to(n: u128, limit: u128) -> Verdict {
+    if n > limit.min(VERIFY_LIMIT) { return Verdict::TooLarge; }
+
+    let square = &BigUint::factorial(n as u64) + 1;
+    match square.exact_sqrt() {
+        Some(k) => Verdict::Square { k },
+        None => Verdict::NotSquare,
+    }
+}
+
+/// The verdict on each `Solution` among `candidates`, in order; the nonsolutions are already
+/// settled.
+pub fn verify_solutions(candidates: &[BrocardCandidate], limit: u128) -> Vec<(u128, Verdict)> {
+    candidates.iter().filter_map(|c| match c {
+        BrocardCandidate::Solution(n) => Some((*n, verify_up_to(*n, limit))),
+        BrocardCandidate::Nonsolution { .. } => None,
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brocard::span::BrocardSpan;
+    use crate::math::prime::primes_from;
+
+    fn square(k: u128) -> Verdict {
+        Verdict::Square { k: BigUint::from(k) }
+    }
+
+    #[test]
+    fn the_known_solutions_are_squares() {
+        assert_eq!(verify(4), square(5));
+        assert_eq!(verify(5), square(11));
+        assert_eq!(verify(7), square(71));
+
+        for n in (0..4).chain([6]).chain(8..200) {
+            assert_eq!(verify(n), Verdict::NotSquare, "{}", n);
+        }
+    }
+
+    #[test]
+    fn the_guard_stops_large_candidates() {
+        assert_eq!(verify(VERIFY_LIMIT + 1), Verdict::TooLarge);
+        assert_eq!(verify(u128::MAX), Verdict::TooLarge);
+        assert_eq!(verify_up_to(7, 6), Verdict::TooLarge);
+        assert_eq!(verify_up_to(7, u128::MAX), square(71));
+    }
+
+    #[test]
+    fn solutions_from_a_span_are_confirmed() {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        BrocardSpan::new(2, 100, primes_from(1000).take(20).collect(), tx).solve();
+        let report = rx.recv().unwrap();
+
+        assert_eq!(verify_solutions(&report.candidates, VERIFY_LIMIT), vec![(4, square(5)), (5, square(11)), (7, square(71))]);
+    }
+
+    #[test]
+    fn too_few_primes_let_a_nonsolution_through() {
+        // 8! + 1 = 40321 = 61 * 661 isn't a square, but it is a residue mod 1013.
+        let (tx, rx) = crossbeam::channel::unbounded();
+        BrocardSpan::new(8, 0, vec![1013], tx).solve();
+        let report = rx.recv().unwrap();
+
+        assert_eq!(verify_solutions(&report.candidates, VERIFY_LIMIT), vec![(8, Verdict::NotSquare)]);
+    }
+}
@@ -1,9 +1,7 @@
-#![feature(const_swap, const_mut_refs)]
-#![feature(duration_abs_diff)]
-
 pub mod montgomery;
 pub mod math;
 pub mod brocard;
+pub mod ring;
 
 // Test Deps
 #[cfg(test)]
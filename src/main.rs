@@ -3,11 +3,20 @@ use std::time::Duration;
 
 const BOUND : u128 = 1_000_000_000;
 
+/// With a path as its argument, the run takes its primes from that prime list (see `PrimeFile`)
+/// rather than finding the next ones above each chunk.
 fn main() {
-    BrocardBroker::new(
+    let broker = BrocardBroker::new(
         2, 
         BOUND, 
         100_000_000, 
         Duration::from_secs(60)
-    ).run_solver(60);
+    );
+
+    let mut broker = match std::env::args().nth(1) {
+        Some(path) => broker.with_source(PrimeFile::read_from_file(&path).expect("couldn't read the prime list")),
+        None => broker,
+    };
+
+    broker.run_solver(60);
 }
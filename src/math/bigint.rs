@@ -0,0 +1,437 @@
+use crate::math::wide_rem;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Shl, Shr};
+use std::str::FromStr;
+
+/// An unsigned integer of any size, for the few places where `u128` runs out: CRT over many
+/// primes, and the exact `n! + 1`.
+///
+/// Limbs are `u64`, least significant first, with no zero limbs at the top, so every value has
+/// exactly one representation and the derived equality is the numeric one. Nothing here is
+/// clever; the hot paths of the crate stay in `u128`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigUint {
+    limbs: Vec<u64>,
+}
+
+impl BigUint {
+    pub fn zero() -> BigUint {
+        BigUint { limbs: vec![] }
+    }
+
+    pub fn one() -> BigUint {
+        BigUint::from(1u128)
+    }
+
+    fn normalized(mut limbs: Vec<u64>) -> BigUint {
+        while limbs.last() == Some(&0) { limbs.pop(); }
+        BigUint { limbs }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// The number of significant bits; `0` for zero.
+    pub fn bits(&self) -> u64 {
+        match self.limbs.last() {
+            Some(top) => 64 * self.limbs.len() as u64 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    /// The value, if it fits.
+    pub fn to_u128(&self) -> Option<u128> {
+        match self.limbs[..] {
+            [] => Some(0),
+            [lo] => Some(lo as u128),
+            [lo, hi] => Some(lo as u128 | (hi as u128) << 64),
+            _ => None,
+        }
+    }
+
+    /// `self * m + a`, in place.
+    pub fn mul_add_small(&mut self, m: u64, a: u64) {
+        let mut carry = a as u128;
+        for limb in self.limbs.iter_mut() {
+            let t = *limb as u128 * m as u128 + carry;
+            *limb = t as u64;
+            carry = t >> 64;
+        }
+        if carry != 0 { self.limbs.push(carry as u64); }
+        if m == 0 { *self = BigUint::from(a as u128); }
+    }
+
+    /// `(self / d, self % d)`.
+    pub fn div_rem_small(&self, d: u64) -> (BigUint, u64) {
+        assert!(d != 0, "division by zero");
+
+        let mut quotient = vec![0; self.limbs.len()];
+        let mut rem = 0u128;
+        for (q, &limb) in quotient.iter_mut().zip(&self.limbs).rev() {
+            let cur = rem << 64 | limb as u128;
+            *q = (cur / d as u128) as u64;
+            rem = cur % d as u128;
+        }
+
+        (BigUint::normalized(quotient), rem as u64)
+    }
+
+    /// `self mod m`, for any nonzero `u128` modulus. Horner's rule from the top limb, with each
+    /// step a 192 by 128 bit remainder.
+    pub fn rem_u128(&self, m: u128) -> u128 {
+        assert!(m != 0, "division by zero");
+
+        self.limbs.iter().rev().fold(0, |r, &limb| {
+            // r < m, so the high half r >> 64 is too, as `wide_rem` needs.
+            wide_rem(r << 64 | limb as u128, r >> 64, m)
+        })
+    }
+
+    /// `n!`, by a product tree: each half of the range is multiplied out on its own and the two
+    /// halves multiplied together, so the big multiplications are between numbers of about the
+    /// same size rather than a growing product and one small factor at a time.
+    pub fn factorial(n: u64) -> BigUint {
+        BigUint::range_product(1, n)
+    }
+
+    /// `lo * (lo + 1) * ... * hi`, or `1` for an empty range.
+    fn range_product(lo: u64, hi: u64) -> BigUint {
+        if hi < lo.saturating_add(16) {
+            let mut acc = BigUint::one();
+            for k in lo..=hi { acc.mul_add_small(k, 0); }
+            return acc;
+        }
+
+        let mid = lo + (hi - lo) / 2;
+        &BigUint::range_product(lo, mid) * &BigUint::range_product(mid + 1, hi)
+    }
+
+    /// The low 64 bits.
+    pub fn low_u64(&self) -> u64 {
+        self.limbs.first().copied().unwrap_or(0)
+    }
+
+    /// `(self / d, self % d)`, by Knuth's algorithm D: estimate each quotient limb from the top
+    /// two limbs of the remainder and the top limb of `d`, shifted so that limb has its top bit
+    /// set, which makes the estimate at most two too big.
+    pub fn div_rem(&self, d: &BigUint) -> (BigUint, BigUint) {
+        assert!(!d.is_zero(), "division by zero");
+
+        if self < d { return (BigUint::zero(), self.clone()); }
+        if let [d] = d.limbs[..] {
+            let (q, r) = self.div_rem_small(d);
+            return (q, BigUint::from(r as u128));
+        }
+
+        let shift = d.limbs.last().unwrap().leading_zeros() as u64;
+        let v = (d << shift).limbs;
+        let mut u = (self << shift).limbs;
+        u.resize(self.limbs.len() + 1, 0);
+
+        let n = v.len();
+        let (v_top, v_next) = (v[n - 1] as u128, v[n - 2] as u128);
+        let mut quotient = vec![0u64; u.len() - n];
+
+        for j in (0..quotient.len()).rev() {
+            let top = (u[j + n] as u128) << 64 | u[j + n - 1] as u128;
+            let mut q = top / v_top;
+            let mut r = top % v_top;
+            while q >> 64 != 0 || q * v_next > (r << 64 | u[j + n - 2] as u128) {
+                q -= 1;
+                r += v_top;
+                if r >> 64 != 0 { break; }
+            }
+
+            // u[j..] -= q * v, with the borrow carried as a signed high word.
+            let mut borrow = 0i128;
+            for i in 0..n {
+                let p = q * v[i] as u128;
+                let t = u[i + j] as i128 - borrow - (p as u64) as i128;
+                u[i + j] = t as u64;
+                borrow = (p >> 64) as i128 - (t >> 64);
+            }
+            let t = u[j + n] as i128 - borrow;
+            u[j + n] = t as u64;
+
+            // Still too big by one; add `v` back.
+            if t < 0 {
+                q -= 1;
+                let mut carry = 0u128;
+                for i in 0..n {
+                    let s = u[i + j] as u128 + v[i] as u128 + carry;
+                    u[i + j] = s as u64;
+                    carry = s >> 64;
+                }
+                u[j + n] = u[j + n].wrapping_add(carry as u64);
+            }
+            quotient[j] = q as u64;
+        }
+
+        u.truncate(n);
+        (BigUint::normalized(quotient), &BigUint::normalized(u) >> shift)
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(v: u128) -> BigUint {
+        BigUint::normalized(vec![v as u64, (v >> 64) as u64])
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &BigUint) -> Ordering {
+        self.limbs.len().cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &BigUint) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let (long, short) = if self.limbs.len() >= other.limbs.len() { (self, other) } else { (other, self) };
+
+        let mut limbs = Vec::with_capacity(long.limbs.len() + 1);
+        let mut carry = false;
+        for (i, &a) in long.limbs.iter().enumerate() {
+            let (s, c1) = a.overflowing_add(short.limbs.get(i).copied().unwrap_or(0));
+            let (s, c2) = s.overflowing_add(carry as u64);
+            limbs.push(s);
+            carry = c1 || c2;
+        }
+        if carry { limbs.push(1); }
+
+        BigUint::normalized(limbs)
+    }
+}
+
+impl Add<u128> for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: u128) -> BigUint {
+        self + &BigUint::from(other)
+    }
+}
+
+/// Schoolbook multiplication. The operands here are at most a few thousand limbs, where anything
+/// cleverer wouldn't pay for itself.
+impl Mul<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() { return BigUint::zero(); }
+
+        let mut limbs = vec![0u64; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let t = a as u128 * b as u128 + limbs[i + j] as u128 + carry;
+                limbs[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            limbs[i + other.limbs.len()] = carry as u64;
+        }
+
+        BigUint::normalized(limbs)
+    }
+}
+
+impl Mul<u128> for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: u128) -> BigUint {
+        self * &BigUint::from(other)
+    }
+}
+
+impl Shl<u64> for &BigUint {
+    type Output = BigUint;
+
+    fn shl(self, bits: u64) -> BigUint {
+        if self.is_zero() { return BigUint::zero(); }
+
+        let (whole, part) = ((bits / 64) as usize, (bits % 64) as u32);
+        let mut limbs = vec![0u64; whole];
+        limbs.extend(&self.limbs);
+        if part > 0 {
+            limbs.push(0);
+            for i in (whole + 1..limbs.len()).rev() {
+                limbs[i] = limbs[i] << part | limbs[i - 1] >> (64 - part);
+            }
+            limbs[whole] <<= part;
+        }
+
+        BigUint::normalized(limbs)
+    }
+}
+
+impl Shr<u64> for &BigUint {
+    type Output = BigUint;
+
+    fn shr(self, bits: u64) -> BigUint {
+        let (whole, part) = ((bits / 64) as usize, (bits % 64) as u32);
+        if whole >= self.limbs.len() { return BigUint::zero(); }
+
+        let mut limbs = self.limbs[whole..].to_vec();
+        if part > 0 {
+            for i in 0..limbs.len() {
+                let next = limbs.get(i + 1).copied().unwrap_or(0);
+                limbs[i] = limbs[i] >> part | next << (64 - part);
+            }
+        }
+
+        BigUint::normalized(limbs)
+    }
+}
+
+/// Decimal, 19 digits at a time.
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+
+        let mut chunks = vec![];
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            let (q, r) = rest.div_rem_small(CHUNK);
+            chunks.push(r);
+            rest = q;
+        }
+
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((top, lower)) => {
+                write!(f, "{}", top)?;
+                lower.iter().rev().try_for_each(|c| write!(f, "{:019}", c))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBigUintError(pub String);
+
+impl fmt::Display for ParseBigUintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "not a decimal integer: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseBigUintError {}
+
+impl FromStr for BigUint {
+    type Err = ParseBigUintError;
+
+    fn from_str(s: &str) -> Result<BigUint, ParseBigUintError> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseBigUintError(s.to_string()));
+        }
+
+        let mut n = BigUint::zero();
+        for chunk in s.as_bytes().chunks(19) {
+            let digits = std::str::from_utf8(chunk).unwrap();
+            n.mul_add_small(10u64.pow(chunk.len() as u32), digits.parse().unwrap());
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::mod_mult;
+
+    #[quickcheck]
+    fn arithmetic_matches_u128(a: u64, b: u64, c: u64) -> bool {
+        let (a, b, c) = (a as u128, b as u128, c as u128);
+        let (x, y) = (BigUint::from(a), BigUint::from(b));
+
+        (&x * &y).to_u128() == Some(a * b)
+            && (&x + &y).to_u128() == Some(a + b)
+            && (&x * &y).rem_u128(c + 1) == a * b % (c + 1)
+            && (&x * &y).cmp(&BigUint::from(c)) == (a * b).cmp(&c)
+    }
+
+    #[quickcheck]
+    fn products_reduce_like_mod_mult(a: u128, b: u128, c: u128, m: u128) -> bool {
+        let m = m | 1;
+        let abc = &(&BigUint::from(a) * &BigUint::from(b)) * c;
+
+        abc.rem_u128(m) == mod_mult(mod_mult(a, b, m), c, m)
+    }
+
+    #[quickcheck]
+    fn division_undoes_multiplication(a: u128, b: u128, c: u128, d: u128, r: u128) -> bool {
+        let d = &(&BigUint::from(d) * &BigUint::from(c)) + 1;
+        let r = BigUint::from(r).div_rem(&d).1;
+        let n = &(&(&BigUint::from(a) * &BigUint::from(b)) * &d) + &r;
+
+        n.div_rem(&d) == (&BigUint::from(a) * &BigUint::from(b), r)
+    }
+
+    #[test]
+    fn division_at_the_limb_edges() {
+        // Limbs near 0, 2^63 and 2^64 are where the quotient estimate is off, and some of these
+        // need the add-back.
+        let edges = [0, 1, 1 << 63, (1 << 63) - 1, u64::MAX - 1, u64::MAX];
+        let numbers = |len: u32| (0..6usize.pow(len)).map(move |mut i| {
+            BigUint::normalized((0..len).map(|_| { let limb = edges[i % 6]; i /= 6; limb }).collect())
+        });
+
+        for n in numbers(4) {
+            for d in numbers(3).filter(|d| !d.is_zero()) {
+                let (q, r) = n.div_rem(&d);
+                assert!(r < d && &(&q * &d) + &r == n, "{} / {}", n, d);
+            }
+        }
+    }
+
+    #[quickcheck]
+    fn shifts_are_multiplication_by_powers_of_two(a: u128, bits: u8) -> bool {
+        let x = BigUint::from(a);
+        let power = &BigUint::one() << bits as u64;
+
+        &x << bits as u64 == &x * &power && &(&x << bits as u64) >> bits as u64 == x
+            && &x >> bits as u64 == x.div_rem(&power).0
+    }
+
+    #[quickcheck]
+    fn the_product_tree_is_the_running_product(n: u16) -> bool {
+        let n = n as u64 % 600;
+        let running = (1..=n).fold(BigUint::one(), |acc, k| &acc * k as u128);
+
+        BigUint::factorial(n) == running
+    }
+
+    #[quickcheck]
+    fn decimal_round_trips(a: u128, b: u128) -> bool {
+        let n = &(&BigUint::from(a) * &BigUint::from(b)) + 7;
+        n.to_string().parse::<BigUint>() == Ok(n)
+    }
+
+    #[test]
+    fn formats_known_values() {
+        let two_128 = &BigUint::from(u128::MAX) + 1;
+
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!(two_128.to_string(), "340282366920938463463374607431768211456");
+        assert_eq!((&two_128 * &two_128).to_string(),
+                   "115792089237316195423570985008687907853269984665640564039457584007913129639936");
+        assert_eq!(two_128.bits(), 129);
+        assert_eq!(two_128.to_u128(), None);
+        // A two limb divisor with almost nothing in its top limb.
+        let d: BigUint = "340282366920938463463374607431768211457".parse().unwrap();
+        let n = &(&d * &d) + &(&d >> 1);
+        assert_eq!(n.div_rem(&d), (d.clone(), &d >> 1));
+        assert_eq!(BigUint::factorial(0), BigUint::one());
+        assert_eq!(BigUint::factorial(34).to_u128(), Some((1..=34).product()));
+        assert!("12a".parse::<BigUint>().is_err() && "".parse::<BigUint>().is_err());
+    }
+}
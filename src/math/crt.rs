@@ -0,0 +1,185 @@
+use crate::math::bigint::BigUint;
+use crate::math::{gcd, mod_add, mod_inv, mod_mult, mod_sub};
+use crate::montgomery::Residue;
+use std::fmt;
+
+/// A residue whose modulus may be too big for `u128`: what CRT gives back once the product of the
+/// moduli outgrows it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigResidue {
+    pub val: BigUint,
+    pub n: BigUint,
+}
+
+impl BigResidue {
+    /// The same class modulo `m`, or `None` unless `m` is a (nonzero) divisor of `n`, since
+    /// otherwise the class mod `n` doesn't decide it.
+    pub fn reduce(&self, m: u128) -> Option<Residue> {
+        if m == 0 || self.n.rem_u128(m) != 0 { return None; }
+        Residue::try_new(self.val.rem_u128(m), m).ok()
+    }
+
+    /// As a `Residue`, if the modulus fits.
+    pub fn to_residue(&self) -> Option<Residue> {
+        Residue::try_new(self.val.to_u128()?, self.n.to_u128()?).ok()
+    }
+}
+
+impl fmt::Display for BigResidue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} mod {}", self.val, self.n)
+    }
+}
+
+/// Two of the moduli share a factor, so there's no telling whether the residues agree on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotCoprime {
+    pub a: u128,
+    pub b: u128,
+}
+
+impl fmt::Display for NotCoprime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "moduli {} and {} are not coprime", self.a, self.b)
+    }
+}
+
+impl std::error::Error for NotCoprime {}
+
+/// Garner's algorithm, one residue at a time.
+///
+/// The value is kept in mixed radix, `x = d_0 + m_0 (d_1 + m_1 (d_2 + ...))` with `d_i < m_i`, so
+/// taking in a new residue mod `m` only needs `x mod m` and the inverse of `m_0 m_1 ... mod m`,
+/// all in `u128`. Nothing multi-precision happens until `value` is asked for.
+#[derive(Debug, Clone, Default)]
+pub struct Garner {
+    moduli: Vec<u128>,
+    digits: Vec<u128>,
+}
+
+impl Garner {
+    pub fn new() -> Garner {
+        Garner::default()
+    }
+
+    /// Takes in `r`, so the value is also `r.val` mod `r.n`. The modulus has to be coprime to
+    /// every one before it.
+    pub fn push(&mut self, r: Residue) -> Result<(), NotCoprime> {
+        let m = r.n;
+
+        // x mod m by Horner on the digits, and m_0 m_1 ... mod m alongside.
+        let (x, prefix) = self.moduli.iter().zip(&self.digits).rev().fold((0, 1 % m), |(x, prefix), (&mi, &di)| {
+            (mod_add(mod_mult(x, mi % m, m), di % m, m), mod_mult(prefix, mi % m, m))
+        });
+        let Some(inverse) = mod_inv(prefix, m) else {
+            let a = *self.moduli.iter().find(|&&mi| gcd(mi, m) != 1).unwrap();
+            return Err(NotCoprime { a, b: m });
+        };
+
+        self.digits.push(mod_mult(mod_sub(r.val % m, x, m), inverse, m));
+        self.moduli.push(m);
+        Ok(())
+    }
+
+    /// The moduli taken in so far.
+    pub fn moduli(&self) -> &[u128] {
+        &self.moduli
+    }
+
+    /// The product of the moduli.
+    pub fn modulus(&self) -> BigUint {
+        self.moduli.iter().fold(BigUint::one(), |acc, &m| &acc * m)
+    }
+
+    /// The least value that has every residue taken in so far.
+    pub fn value(&self) -> BigUint {
+        self.moduli.iter().zip(&self.digits).rev().fold(BigUint::zero(), |acc, (&m, &d)| &(&acc * m) + d)
+    }
+
+    pub fn residue(&self) -> BigResidue {
+        BigResidue { val: self.value(), n: self.modulus() }
+    }
+}
+
+/// Combines residues with pairwise coprime moduli into the one residue modulo their product. An
+/// `Elt` converts directly, so the residues can come straight out of several `Space`s.
+pub fn crt<R: Into<Residue>>(residues: impl IntoIterator<Item = R>) -> Result<BigResidue, NotCoprime> {
+    let mut garner = Garner::new();
+    for r in residues {
+        garner.push(r.into())?;
+    }
+    Ok(garner.residue())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::prime::primes_from;
+    use crate::montgomery::Space;
+
+    #[quickcheck]
+    fn recovers_what_it_was_given(a: u128, b: u64, start: u32) -> bool {
+        // Five primes above 2^32 take the product past 2^160, so a*b + 1 always fits under it.
+        let x = &(&BigUint::from(a) * b as u128) + 1;
+        let primes : Vec<u128> = primes_from(start as u128 + (1 << 32)).take(5).collect();
+
+        let combined = crt(primes.iter().map(|&p| Residue::new(x.rem_u128(p), p))).unwrap();
+        combined.val == x && primes.iter().all(|&p| combined.reduce(p) == Some(Residue::new(x.rem_u128(p), p)))
+    }
+
+    #[quickcheck]
+    fn small_moduli_match_brute_force(a: u16, b: u16, c: u16) -> bool {
+        let residues = [Residue::new(a as u128, 7), Residue::new(b as u128, 9), Residue::new(c as u128, 10)];
+        let expected = (0..630).find(|x| residues.iter().all(|r| x % r.n == r.val)).unwrap();
+
+        crt(residues).unwrap().to_residue() == Some(Residue::new(expected, 630))
+    }
+
+    #[test]
+    fn factorials_across_spaces_combine_to_the_exact_value() {
+        // 40! + 1 has 48 digits, past u128, and 20 primes above 1000 cover it.
+        let exact = (1..=40).fold(BigUint::one(), |acc, k| &acc * k as u128);
+        let exact = &exact + 1;
+
+        let spaces : Vec<Space<64, u64>> = primes_from(1_000).take(20).map(Space::new).collect();
+        let residues = spaces.iter().map(|space| space.sublinear_factorial(40) + space.enter(1));
+
+        assert_eq!(crt(residues).unwrap().val, exact);
+        assert_eq!(exact.to_string(), "815915283247897734345611269596115894272000000001");
+    }
+
+    #[test]
+    fn garner_is_incremental() {
+        let x = BigUint::from(u128::MAX);
+        let x = &(&x * &x) + 12_345;
+        let mut garner = Garner::new();
+
+        for p in primes_from(1 << 62).take(6) {
+            garner.push(Residue::new(x.rem_u128(p), p)).unwrap();
+            let partial = garner.residue();
+
+            // The value is always the least one, so below the modulus, and agrees with x on it.
+            assert!(partial.val < partial.n);
+            assert!(garner.moduli().iter().all(|&q| partial.val.rem_u128(q) == x.rem_u128(q)));
+        }
+        assert_eq!(garner.value(), x);
+    }
+
+    #[test]
+    fn reduces_only_to_divisors() {
+        let r = crt([Residue::new(2, 3), Residue::new(3, 5), Residue::new(2, 7)]).unwrap();
+        assert_eq!(r.val, BigUint::from(23u128));
+
+        assert_eq!(r.reduce(15), Some(Residue::new(8, 15)));
+        assert_eq!(r.reduce(105), Some(Residue::new(23, 105)));
+        assert_eq!(r.reduce(11), None);
+        assert_eq!(r.reduce(0), None);
+        assert_eq!(BigResidue { val: BigUint::zero(), n: BigUint::zero() }.to_residue(), None);
+    }
+
+    #[test]
+    fn rejects_moduli_with_a_common_factor() {
+        let residues = [Residue::new(1, 15), Residue::new(2, 7), Residue::new(3, 21)];
+        assert_eq!(crt(residues), Err(NotCoprime { a: 15, b: 21 }));
+    }
+}
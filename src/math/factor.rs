@@ -0,0 +1,200 @@
+use crate::math::gcd;
+use crate::math::prime::{is_prime, primes_from};
+use crate::math::square::exact_sqrt;
+use crate::montgomery::{Space, Word};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// The primes in the trial division table are all below this.
+pub const TABLE_BOUND: u128 = 1 << 16;
+
+/// How far `factor` trial divides before handing over to Pollard's rho.
+pub const TRIAL_BOUND: u128 = 1 << 12;
+
+/// The primes below `TABLE_BOUND`, sieved once on first use.
+fn table() -> &'static [u128] {
+    static TABLE: OnceLock<Vec<u128>> = OnceLock::new();
+    TABLE.get_or_init(|| primes_from(2).take_while(|&p| p < TABLE_BOUND).collect())
+}
+
+/// The prime factorization of `n`, as each prime with its exponent, smallest first. The product
+/// is always `n`: `1` has no factors, and `0` is given as `{0: 1}`.
+///
+/// Factors below `TRIAL_BOUND` come out by trial division. What's left is split by Pollard's rho
+/// until `is_prime` accepts every piece.
+pub fn factor(n: u128) -> BTreeMap<u128, u32> {
+    if n == 0 { return BTreeMap::from([(0, 1)]); }
+
+    let (mut factors, rest) = trial_divide(n, TRIAL_BOUND);
+    split(rest, &mut factors);
+    factors
+}
+
+/// Divides out the primes below `bound` (at most `TABLE_BOUND`), returning those found and the
+/// cofactor left over, which has no prime factor below `bound`. Should the cofactor turn out to be
+/// prime on the way, it goes in with the factors, and the cofactor is `1`.
+pub fn trial_divide(n: u128, bound: u128) -> (BTreeMap<u128, u32>, u128) {
+    debug_assert!(bound <= TABLE_BOUND);
+
+    let mut n = n;
+    let mut factors = BTreeMap::new();
+
+    // Should the primes run out of reach of `n` before `bound`, everything below its square root
+    // is gone, and what's left is prime.
+    let mut reached_bound = true;
+    for &p in table().iter().take_while(|&&p| p < bound) {
+        if p * p > n {
+            reached_bound = false;
+            break;
+        }
+
+        while n.is_multiple_of(p) {
+            n /= p;
+            *factors.entry(p).or_insert(0) += 1;
+        }
+    }
+
+    if n > 1 && !reached_bound {
+        *factors.entry(n).or_insert(0) += 1;
+        n = 1;
+    }
+
+    (factors, n)
+}
+
+/// Adds the factorization of `n`, which has no small factors, to `factors`.
+fn split(n: u128, factors: &mut BTreeMap<u128, u32>) {
+    if n == 1 { return; }
+
+    if is_prime(n) {
+        *factors.entry(n).or_insert(0) += 1;
+        return;
+    }
+
+    let d = rho(n);
+    split(d, factors);
+    split(n / d, factors);
+}
+
+/// A nontrivial factor of the odd composite `n`, by Pollard's rho with Brent's cycle finding, in
+/// the narrowest Montgomery space that holds `n`.
+pub fn rho(n: u128) -> u128 {
+    debug_assert!(n & 1 == 1 && !is_prime(n));
+
+    // A perfect square of a prime would send every walk around the same cycle as its root.
+    if let Some(root) = exact_sqrt(n) { return root; }
+
+    if n >> 64 == 0 {
+        brent(&Space::<64, u64>::new(n))
+    } else {
+        brent(&Space::<128>::new(n))
+    }
+}
+
+/// Brent's variant of rho: walk `y -> y^2 + c`, compare against a saved point that moves up at
+/// every power of two, and batch `M` differences into one product before taking a gcd.
+///
+/// The walk is on the Montgomery forms, which is a walk of the same shape (it's a pseudorandom
+/// map mod `n` either way), and since `R` is a unit, multiplying the differences by it doesn't
+/// change any gcd with `n`.
+fn brent<const R_EXP: usize, W: Word>(space: &Space<R_EXP, W>) -> u128 {
+    const M: usize = 128;
+    let n = space.n.to_u128();
+
+    for c in 1.. {
+        let c = space.enter(c).val;
+        let f = |y: W| space.add(space.mul(y, y), c);
+
+        let (mut x, mut y, mut ys) = (space.enter(2).val, space.enter(2).val, space.enter(2).val);
+        let mut q = space.enter(1).val;
+        let mut g = 1;
+        let mut r = 1;
+
+        while g == 1 {
+            x = y;
+            for _ in 0..r { y = f(y); }
+
+            let mut k = 0;
+            while k < r && g == 1 {
+                ys = y;
+                for _ in 0..M.min(r - k) {
+                    y = f(y);
+                    q = space.mul(q, space.sub(x, y));
+                }
+                g = gcd(q.to_u128() % n, n);
+                k += M;
+            }
+            r *= 2;
+        }
+
+        // The batch overshot and took in a multiple of `n`; step through it one at a time.
+        if g == n {
+            loop {
+                ys = f(ys);
+                g = gcd(space.sub(x, ys).to_u128() % n, n);
+                if g > 1 { break; }
+            }
+        }
+
+        if g != n { return g; }
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product(factors: &BTreeMap<u128, u32>) -> u128 {
+        factors.iter().map(|(&p, &e)| p.pow(e)).product()
+    }
+
+    fn all_prime(factors: &BTreeMap<u128, u32>) -> bool {
+        factors.keys().all(|&p| is_prime(p))
+    }
+
+    #[quickcheck]
+    fn factors_multiply_back(n: u64) -> bool {
+        let n = n as u128;
+        let factors = factor(n);
+
+        product(&factors) == n && (n == 0 || all_prime(&factors))
+    }
+
+    #[quickcheck]
+    fn wide_factors_multiply_back(a: u64, b: u64, c: u16) -> bool {
+        // Two factors of up to 40 bits keep rho quick, and the third puts small ones back in.
+        let n = (a as u128 % (1 << 40) + 1) * (b as u128 % (1 << 40) + 1) * (c as u128 + 1);
+        let factors = factor(n);
+
+        product(&factors) == n && all_prime(&factors)
+    }
+
+    #[test]
+    fn splits_hard_cases() {
+        let cases : [(u128, &[(u128, u32)]); 5] = [
+            // Carmichael, strong pseudoprime, square of a prime, prime power, and a semiprime
+            // with both factors past 2^40.
+            (561, &[(3, 1), (11, 1), (17, 1)]),
+            (3_215_031_751, &[(151, 1), (751, 1), (28_351, 1)]),
+            (4_294_967_311 * 4_294_967_311, &[(4_294_967_311, 2)]),
+            (1_000_003u128.pow(5), &[(1_000_003, 5)]),
+            (1_099_511_627_791 * 1_099_511_627_803, &[(1_099_511_627_791, 1), (1_099_511_627_803, 1)]),
+        ];
+
+        for (n, expected) in cases {
+            assert_eq!(factor(n), expected.iter().copied().collect(), "{}", n);
+        }
+        assert_eq!(factor(1), BTreeMap::new());
+        assert_eq!(factor((1 << 127) - 1), BTreeMap::from([((1 << 127) - 1, 1)]));
+    }
+
+    #[test]
+    fn trial_division_leaves_only_large_factors() {
+        let n = 2 * 3 * 5 * 65_537 * 65_537;
+        let (small, rest) = trial_divide(n, 1 << 10);
+
+        assert_eq!(small, BTreeMap::from([(2, 1), (3, 1), (5, 1)]));
+        assert_eq!(rest, 65_537 * 65_537);
+    }
+}
@@ -23,6 +23,55 @@ impl LegendreSymbol {
     }
 }
 
+/// The Jacobi symbol `(a/n)` for odd `n`, which is the Legendre symbol when `n` is prime.
+///
+/// This is the binary algorithm: strip factors of two from `a` (each flipping the sign when `n =
+/// 3, 5 mod 8`), then use quadratic reciprocity to keep `a >= n` (flipping when both are `3 mod
+/// 4`) and subtract. Everything is shifts, subtractions and comparisons, so unlike Euler's
+/// criterion there is no exponentiation, and unlike the textbook version there is no division
+/// after the first reduction.
+pub fn jacobi(a: u128, n: u128) -> LegendreSymbol {
+    debug_assert!(n & 1 == 1, "the jacobi symbol needs an odd modulus");
+
+    let mut a = a % n;
+    let mut n = n;
+    let mut negative = false;
+
+    if a == 0 {
+        return if n == 1 { LegendreSymbol::Residue } else { LegendreSymbol::Divisor };
+    }
+
+    loop {
+        let twos = a.trailing_zeros();
+        a >>= twos;
+        // (2/n) = -1 exactly when n = 3, 5 mod 8
+        if twos & 1 == 1 && matches!(n & 7, 3 | 5) {
+            negative = !negative;
+        }
+
+        // Both odd now. Reciprocity lets us swap them so the larger is on top.
+        if a < n {
+            if a & 3 == 3 && n & 3 == 3 {
+                negative = !negative;
+            }
+            std::mem::swap(&mut a, &mut n);
+        }
+
+        // (a/n) = ((a - n)/n), and a - n is even, so the next pass strips at least one two.
+        a -= n;
+
+        if a == 0 {
+            return if n != 1 {
+                LegendreSymbol::Divisor
+            } else if negative {
+                LegendreSymbol::Nonresidue
+            } else {
+                LegendreSymbol::Residue
+            };
+        }
+    }
+}
+
 impl Mul for LegendreSymbol {
     type Output = Self;
 
@@ -41,5 +90,29 @@ impl Mul for LegendreSymbol {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::math::gcd;
+
+    // A few primes of different sizes and residues mod 8, so every branch of the sign rules gets
+    // exercised.
+    const PRIMES: [u128; 6] = [3, 5, 7, 17, 1_000_000_007, (1 << 61) - 1];
+
+    #[quickcheck]
+    fn jacobi_is_naive_legendre_for_primes(a: u128) -> bool {
+        PRIMES.iter().all(|&p| jacobi(a, p) == LegendreSymbol::naive_legendre(a, p))
+    }
+
+    #[quickcheck]
+    fn jacobi_is_multiplicative_in_the_modulus(a: u128, m: u8, n: u8) -> bool {
+        let (m, n) = ((m as u128) | 1, (n as u128) | 1);
+        if gcd(m, n) != 1 { return true; }
 
+        jacobi(a, m * n) == jacobi(a, m) * jacobi(a, n)
+    }
+
+    #[test]
+    fn jacobi_of_zero() {
+        assert_eq!(jacobi(0, 1), LegendreSymbol::Residue);
+        assert_eq!(jacobi(21, 7), LegendreSymbol::Divisor);
+    }
 }
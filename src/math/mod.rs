@@ -1,5 +1,10 @@
 pub mod prime;
+pub mod sieve;
+pub mod factor;
 pub mod legendre;
+pub mod bigint;
+pub mod crt;
+pub mod square;
 
 // TODO: This almost certainly exists somewhere already
 #[inline] pub const fn gcd(a: u128, b: u128) -> u128 {
@@ -13,44 +18,167 @@ pub mod legendre;
     a
 }
 
-#[inline] pub const fn mod_inverse(n: u128, r: u128) -> Option<u128> {
-    let mut t = 0u128;
-    let mut new_t = 1u128;
-    let mut r = r;
-    let mut new_r = n;
+/// The inverse of `a` modulo `n`; the same as `mod_inv`.
+#[inline] pub const fn mod_inverse(a: u128, n: u128) -> Option<u128> {
+    mod_inv(a, n)
+}
+
+/// Adds `a` and `b` modulo `n` without overflowing, assuming both are already reduced mod `n`.
+#[inline] pub const fn mod_add(a: u128, b: u128, n: u128) -> u128 {
+    // `a + b` can exceed 2^128 when `n` is close to it, so compare against the distance to `n`
+    // instead of adding first.
+    if a >= n - b { a - (n - b) } else { a + b }
+}
+
+/// Subtracts `b` from `a` modulo `n`, assuming both are already reduced mod `n`.
+#[inline] pub const fn mod_sub(a: u128, b: u128, n: u128) -> u128 {
+    if a >= b { a - b } else { n - (b - a) }
+}
+
+/// The full 256b product of two 128b numbers, returned as `(lo, hi)`. This is schoolbook
+/// multiplication on 64b limbs, since there is no native 128x128 multiply to lean on.
+#[inline] pub const fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+
+    let (a_lo, a_hi) = (a & MASK, a >> 64);
+    let (b_lo, b_hi) = (b & MASK, b >> 64);
+
+    let lo_lo = a_lo * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_lo = a_hi * b_lo;
+    let hi_hi = a_hi * b_hi;
+
+    // The middle column collects the cross terms and the carry out of the low column, each of
+    // which fits in 64b, so the sum fits comfortably in 128b.
+    let mid = (lo_lo >> 64) + (lo_hi & MASK) + (hi_lo & MASK);
 
-    while new_r != 0 {
-        let quotient = r / new_r;
-        // wrapping_sub works by wrapping around on overflow, so we don't need to check for
-        // negative values
-        t = t.wrapping_sub(quotient.wrapping_mul(new_t));
-        std::mem::swap(&mut t, &mut new_t);
-        r = r.wrapping_sub(quotient.wrapping_mul(new_r));
-        std::mem::swap(&mut r, &mut new_r);
+    let lo = (lo_lo & MASK) | (mid << 64);
+    let hi = hi_hi + (lo_hi >> 64) + (hi_lo >> 64) + (mid >> 64);
+
+    (lo, hi)
+}
+
+/// The inverse of `a` modulo an odd `n`, or `None` if `a` isn't a unit.
+///
+/// This is the binary extended Euclidean algorithm, which only ever adds, subtracts and halves, so
+/// unlike `mod_inverse` it never needs a sign and works across the whole `u128` range. Halving
+/// mod `n` is possible because `n` is odd: if `x` is odd then `x + n` is even.
+pub const fn binary_mod_inverse(a: u128, n: u128) -> Option<u128> {
+    if n == 1 { return Some(0); }
+
+    let a = a % n;
+    if gcd(a, n) != 1 { return None; }
+
+    const fn half(x: u128, n: u128) -> u128 {
+        // (x + n) / 2 without overflowing, when both are odd.
+        if x & 1 == 0 { x >> 1 } else { (x >> 1) + (n >> 1) + 1 }
     }
 
-    if r > 1 { return None; }
+    // Invariants: u = a * x1 mod n, v = a * x2 mod n
+    let mut u = a;
+    let mut v = n;
+    let mut x1 = 1;
+    let mut x2 = 0;
+
+    while u != 1 && v != 1 {
+        while u & 1 == 0 {
+            u >>= 1;
+            x1 = half(x1, n);
+        }
+        while v & 1 == 0 {
+            v >>= 1;
+            x2 = half(x2, n);
+        }
+        if u >= v {
+            u -= v;
+            x1 = mod_sub(x1, x2, n);
+        } else {
+            v -= u;
+            x2 = mod_sub(x2, x1, n);
+        }
+    }
 
-    Some(t)
+    if u == 1 { Some(x1) } else { Some(x2) }
 }
 
+/// `a * b mod n` for any `u128` operands: the full 256b product from `widening_mul`, reduced by
+/// `wide_rem`.
 #[inline] pub const fn mod_mult(a: u128, b: u128, n: u128) -> u128 {
+    let (lo, hi) = widening_mul(a % n, b % n);
+    wide_rem(lo, hi, n)
+}
+
+/// `hi * 2^128 + lo mod n`, where `hi < n`.
+///
+/// This is Knuth's algorithm D with 64b digits, specialised to a two digit divisor. Shifting `n`
+/// up until its top bit is set (and the dividend along with it) makes each quotient digit
+/// estimate from the top two digits of the divisor exact after at most two corrections, and since
+/// `hi < n` the quotient only has two digits. We throw the quotient away and keep the remainder.
+#[inline] pub const fn wide_rem(lo: u128, hi: u128, n: u128) -> u128 {
+    if hi == 0 { return lo % n; }
+
+    let s = n.leading_zeros();
+    let v = n << s;
+    let (u_hi, u_lo) = if s == 0 { (hi, lo) } else { ((hi << s) | (lo >> (128 - s)), lo << s) };
+
+    let r = rem_3_by_2(u_hi, (u_lo >> 64) as u64, v);
+    let r = rem_3_by_2(r, u_lo as u64, v);
+
+    r >> s
+}
+
+/// `u_hi * 2^64 + u_lo mod v`, where `v` has its top bit set and `u_hi < v`, so the quotient is a
+/// single 64b digit.
+#[inline] const fn rem_3_by_2(u_hi: u128, u_lo: u64, v: u128) -> u128 {
+    const B: u128 = 1 << 64;
+
+    let v1 = v >> 64;
+    let v0 = v & (B - 1);
+
+    // Estimate the digit from the top of each, which can only overshoot...
+    let (mut q, mut r) = if u_hi >> 64 == v1 {
+        (B - 1, u_hi - (B - 1) * v1)
+    } else {
+        (u_hi / v1, u_hi % v1)
+    };
+
+    // ...and bring it down until `q * v` fits under the dividend. Once `r` reaches `B` the test
+    // can't pass any more.
+    while r < B && q * v0 > (r << 64) | u_lo as u128 {
+        q -= 1;
+        r += v1;
+    }
+
+    // The true remainder is below `v`, so it's enough to work mod 2^128.
+    ((u_hi << 64) | u_lo as u128).wrapping_sub(q.wrapping_mul(v))
+}
+
+/// The original double-and-add `mod_mult`, one bit of `b` at a time. Much slower than `mod_mult`,
+/// but simple enough to be obviously right, so it's kept as a reference to test against.
+#[inline] pub const fn mod_mult_reference(a: u128, b: u128, n: u128) -> u128 {
     let mut result = 0;
     let mut a = a % n;
     let mut b = b % n;
     while b > 0 {
         if b & 1 == 1 {
-            result = (result + a) % n;
+            result = mod_add(result, a, n);
         }
-        a = (a << 1) % n;
-        b = b >> 1;
+        a = mod_add(a, a, n);
+        b >>= 1;
     }
     result
 }
 
-#[inline] pub const fn mod_inv(a: u128, n: u128) -> u128 {
-    let (x, _) = extended_gcd(a, n);
-    (x % n + n) % n
+/// The inverse of `a` modulo `n`, or `None` if `a` isn't a unit (or `n` is zero). Unlike
+/// `binary_mod_inverse`, `n` can be even.
+#[inline] pub const fn mod_inv(a: u128, n: u128) -> Option<u128> {
+    if n == 0 { return None; }
+
+    let (g, x, _) = extended_gcd(a % n, n);
+    if g != 1 { return None; }
+
+    let r = x.unsigned_abs() % n;
+    Some(if x >= 0 || r == 0 { r } else { n - r })
 }
 
 #[inline] pub const fn mod_exp(a: u128, k: u128, n: u128) -> u128 {
@@ -69,29 +197,129 @@ pub mod legendre;
     result
 }
 
-#[inline] pub const fn extended_gcd(a: u128, b: u128) -> (u128, u128) {
-    let mut s = 0;
-    let mut t = 1;
-    let mut r = b;
-
-    let mut old_s = 1;
-    let mut old_t = 0;
-    let mut old_r = a;
+/// `(g, x, y)` with `g = gcd(a, b)` and `a x + b y = g`.
+///
+/// The coefficients are those of the extended Euclidean algorithm, which keeps `|x| <= b / 2g`
+/// and `|y| <= a / 2g` (short of `a` or `b` being zero, or dividing the other), so they fit in an
+/// `i128` for any pair of `u128`s. The intermediate coefficients are only ever that large too,
+/// except for the pair the last step produces and throws away, which can reach `b / g`. Working
+/// with wrapping arithmetic makes everything right mod 2^128, and so exactly right for whatever
+/// fits.
+#[inline] pub const fn extended_gcd(a: u128, b: u128) -> (u128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    let (mut old_t, mut t) = (0i128, 1i128);
 
     while r != 0 {
         let quotient = old_r / r;
-        let temp = r;
-        r = old_r - quotient * r;
-        old_r = temp;
+        (old_r, r) = (r, old_r - quotient * r);
 
-        let temp = s;
-        s = old_s - quotient * s;
-        old_s = temp;
+        let q = quotient as i128;
+        (old_s, s) = (s, old_s.wrapping_sub(q.wrapping_mul(s)));
+        (old_t, t) = (t, old_t.wrapping_sub(q.wrapping_mul(t)));
+    }
+
+    (old_r, old_s, old_t)
+}
 
-        let temp = t;
-        t = old_t - quotient * t;
-        old_t = temp;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::montgomery::test_case::WideTestCase;
+
+    /// `a x + b y == g`, in 256 bits. The coefficients have opposite signs (or one is zero), so
+    /// it's a difference of two unsigned products.
+    fn bezout_holds(a: u128, b: u128) -> bool {
+        let (g, x, y) = extended_gcd(a, b);
+        let ax = widening_mul(a, x.unsigned_abs());
+        let by = widening_mul(b, y.unsigned_abs());
+
+        let (pos, neg) = match (x < 0, y < 0) {
+            (false, true) => (ax, by),
+            (true, false) => (by, ax),
+            (false, false) => return x as u128 * a + y as u128 * b == g,
+            (true, true) => return false,
+        };
+        let (lo, borrow) = pos.0.overflowing_sub(neg.0);
+        let hi = pos.1.wrapping_sub(neg.1).wrapping_sub(borrow as u128);
+
+        g == gcd(a, b) && lo == g && hi == 0
     }
 
-    (old_s, old_t)
+    #[quickcheck]
+    fn bezout_identity(a: u128, b: u128) -> bool {
+        bezout_holds(a, b)
+    }
+
+    #[quickcheck]
+    fn wide_bezout_identity(tc: WideTestCase, shift: u8) -> bool {
+        // Full width operands, one of them shifted so the sizes differ too.
+        bezout_holds(tc.a, tc.n) && bezout_holds(tc.b >> (shift % 128), tc.a) && bezout_holds(tc.n, tc.n - 1)
+    }
+
+    #[test]
+    fn bezout_at_the_edges() {
+        for (a, b) in [(0, 0), (0, 7), (7, 0), (u128::MAX, 1), (u128::MAX, u128::MAX), (u128::MAX, u128::MAX - 1),
+                       (1 << 127, (1 << 127) - 1), (u128::MAX, 1 << 127), (6, 4), (4, 6)] {
+            assert!(bezout_holds(a, b), "{} {}", a, b);
+        }
+    }
+
+    #[quickcheck]
+    fn mod_inv_inverts(tc: WideTestCase, even: bool) -> bool {
+        let n = if even { tc.n ^ 1 } else { tc.n };
+
+        match mod_inv(tc.a, n) {
+            Some(inv) => inv < n && mod_mult(tc.a, inv, n) == 1 % n,
+            None => gcd(tc.a, n) != 1,
+        }
+    }
+
+    #[quickcheck]
+    fn mod_inv_is_binary_mod_inverse(tc: WideTestCase) -> bool {
+        mod_inv(tc.a, tc.n) == binary_mod_inverse(tc.a, tc.n) && mod_inverse(tc.a, tc.n) == mod_inv(tc.a, tc.n)
+    }
+
+    #[test]
+    fn mod_inv_without_an_inverse() {
+        assert_eq!(mod_inv(6, 9), None);
+        assert_eq!(mod_inv(5, 0), None);
+        assert_eq!(mod_inv(5, 1), Some(0));
+        assert_eq!(mod_inv(3, 10), Some(7));
+    }
+
+    #[quickcheck]
+    fn mod_mult_is_reference(a: u128, b: u128, n: u128) -> bool {
+        n == 0 || mod_mult(a, b, n) == mod_mult_reference(a, b, n)
+    }
+
+    /// A full-width case stretched over every modulus size, odd and even.
+    #[quickcheck]
+    fn wide_mod_mult_is_reference(tc: WideTestCase, shift: u8, even: bool) -> bool {
+        let WideTestCase { a, b, n } = tc;
+        let n = (n >> (shift % 127)) & !(even as u128);
+
+        mod_mult(a, b, n) == mod_mult_reference(a, b, n)
+    }
+
+    #[quickcheck]
+    fn wide_rem_is_reference(tc: WideTestCase, shift: u8) -> bool {
+        // hi * 2^128 + lo = hi * (2^128 - 1 + 1) + lo
+        let WideTestCase { a: lo, b: hi, n } = tc;
+        let n = n >> (shift % 127);
+        let hi = hi % n;
+
+        let r_max = u128::MAX % n;
+        let expected = mod_add(mod_add(mod_mult_reference(hi, r_max, n), hi, n), lo % n, n);
+
+        wide_rem(lo, hi, n) == expected
+    }
+
+    #[test]
+    fn mod_mult_at_the_top_of_the_range() {
+        let n = u128::MAX;
+        assert_eq!(mod_mult(n - 1, n - 1, n), 1);
+        assert_eq!(mod_mult(1 << 127, 2, n), 1);
+        assert_eq!(mod_exp(2, 128, n), 1);
+    }
 }
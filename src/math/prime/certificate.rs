@@ -0,0 +1,279 @@
+use crate::math::factor::{factor, trial_divide};
+use crate::math::prime::is_prime;
+use crate::math::{gcd, mod_exp};
+use crate::math::square::isqrt;
+use std::fmt;
+use std::str::FromStr;
+
+/// Primes below this are certified by trial division alone.
+pub const TRIAL_LIMIT: u128 = 1 << 20;
+
+/// The bound on the small prime factors of `p - 1` that `certify` looks for before deciding
+/// between Pocklington and Pratt.
+const POCKLINGTON_TRIAL: u128 = 1 << 16;
+
+/// A proof that a number is prime, checkable by `verify` with nothing more than modular
+/// exponentiation and trial division, so without trusting `is_prime`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Certificate {
+    /// A prime below `TRIAL_LIMIT`; no divisor up to its square root.
+    Small(u128),
+    /// Pratt's certificate: `witness` has order exactly `p - 1` mod `p`, which only a prime
+    /// allows. `factors` is the full factorization of `p - 1`, each prime with its own
+    /// certificate.
+    Pratt { p: u128, witness: u128, factors: Vec<(Certificate, u32)> },
+    /// The Pocklington-Lehmer certificate: a factored part `F` of `p - 1` with `F^2 > p`, and for
+    /// each prime `q` dividing `F` a witness `a` with `a^(p-1) = 1` and `gcd(a^((p-1)/q) - 1, p) =
+    /// 1`. Then every prime factor of `p` is above `sqrt(p)`, so there is only the one.
+    Pocklington { p: u128, factors: Vec<(Certificate, u32, u128)> },
+}
+
+/// A certificate for `p`, or `None` if `p` isn't prime.
+///
+/// `p - 1` is first cleared of its factors below `POCKLINGTON_TRIAL`. If those make up more than
+/// the square root of `p`, that's enough for Pocklington; otherwise the rest of `p - 1` is
+/// factored as well, and the certificate is Pratt's.
+pub fn certify(p: u128) -> Option<Certificate> {
+    if !is_prime(p) { return None; }
+    if p < TRIAL_LIMIT { return Some(Certificate::Small(p)); }
+
+    let (small, rest) = trial_divide(p - 1, POCKLINGTON_TRIAL);
+    let factored = (p - 1) / rest;
+
+    if factored.checked_mul(factored).is_none_or(|f2| f2 > p) {
+        let factors = small.into_iter().map(|(q, e)| {
+            let witness = (2..).find(|&a| pocklington_holds(p, a, q)).unwrap();
+            Some((certify(q)?, e, witness))
+        }).collect::<Option<_>>()?;
+
+        return Some(Certificate::Pocklington { p, factors });
+    }
+
+    let mut factors = small;
+    factors.extend(factor(rest));
+
+    // A prime has phi(p - 1) generators, and the first is never far off.
+    let witness = (2..p).find(|&a| {
+        mod_exp(a, p - 1, p) == 1 && factors.keys().all(|&q| mod_exp(a, (p - 1) / q, p) != 1)
+    })?;
+    let factors = factors.into_iter().map(|(q, e)| Some((certify(q)?, e))).collect::<Option<_>>()?;
+
+    Some(Certificate::Pratt { p, witness, factors })
+}
+
+fn pocklington_holds(p: u128, a: u128, q: u128) -> bool {
+    mod_exp(a, p - 1, p) == 1 && gcd((mod_exp(a, (p - 1) / q, p) + p - 1) % p, p) == 1
+}
+
+impl Certificate {
+    /// The number this certifies.
+    pub fn prime(&self) -> u128 {
+        match self {
+            Certificate::Small(p) => *p,
+            Certificate::Pratt { p, .. } | Certificate::Pocklington { p, .. } => *p,
+        }
+    }
+
+    /// Checks the certificate, and every one it leans on, from scratch.
+    pub fn verify(&self) -> bool {
+        match self {
+            Certificate::Small(p) => {
+                let p = *p;
+                (2..TRIAL_LIMIT).contains(&p) && (2..=isqrt(p)).all(|d| !p.is_multiple_of(d))
+            }
+            Certificate::Pratt { p, witness, factors } => {
+                let p = *p;
+                p > 2
+                    && product(factors.iter().map(|(c, e)| (c.prime(), *e))) == Some(p - 1)
+                    && mod_exp(*witness, p - 1, p) == 1
+                    && factors.iter().all(|(c, _)| c.verify() && mod_exp(*witness, (p - 1) / c.prime(), p) != 1)
+            }
+            Certificate::Pocklington { p, factors } => {
+                let p = *p;
+                let Some(f) = product(factors.iter().map(|(c, e, _)| (c.prime(), *e))) else { return false; };
+
+                p > 2
+                    && (p - 1).is_multiple_of(f)
+                    && f.checked_mul(f).is_none_or(|f2| f2 > p)
+                    && factors.iter().all(|(c, _, a)| c.verify() && pocklington_holds(p, *a, c.prime()))
+            }
+        }
+    }
+}
+
+/// `prod q^e`, or `None` if it overflows.
+fn product(factors: impl Iterator<Item = (u128, u32)>) -> Option<u128> {
+    factors.into_iter().try_fold(1u128, |acc, (q, e)| acc.checked_mul(q.checked_pow(e)?))
+}
+
+/// A compact text form, which `FromStr` reads back: a small prime is written as itself, Pratt's
+/// as `p=P<witness>(q^e,...)` and Pocklington's as `p=L(q^e@<witness>,...)`, with each `q` in the
+/// same form.
+impl fmt::Display for Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Certificate::Small(p) => write!(f, "{}", p),
+            Certificate::Pratt { p, witness, factors } => {
+                write!(f, "{}=P{}(", p, witness)?;
+                for (i, (c, e)) in factors.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, "{}^{}", c, e)?;
+                }
+                write!(f, ")")
+            }
+            Certificate::Pocklington { p, factors } => {
+                write!(f, "{}=L(", p)?;
+                for (i, (c, e, a)) in factors.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, "{}^{}@{}", c, e, a)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCertificateError(pub String);
+
+impl fmt::Display for ParseCertificateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bad certificate: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseCertificateError {}
+
+impl FromStr for Certificate {
+    type Err = ParseCertificateError;
+
+    fn from_str(s: &str) -> Result<Certificate, ParseCertificateError> {
+        let mut parser = Parser { s, at: 0 };
+        let c = parser.certificate()?;
+        if parser.at != s.len() { return Err(parser.error("trailing input")); }
+        Ok(c)
+    }
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, what: &str) -> ParseCertificateError {
+        ParseCertificateError(format!("{} at {} in {:?}", what, self.at, self.s))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let matched = self.s[self.at..].starts_with(c);
+        if matched { self.at += c.len_utf8(); }
+        matched
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseCertificateError> {
+        if self.eat(c) { Ok(()) } else { Err(self.error(&format!("expected {:?}", c))) }
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, ParseCertificateError> {
+        let digits = self.s[self.at..].bytes().take_while(u8::is_ascii_digit).count();
+        let n = self.s[self.at..self.at + digits].parse().map_err(|_| self.error("expected a number"))?;
+        self.at += digits;
+        Ok(n)
+    }
+
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, ParseCertificateError>) -> Result<Vec<T>, ParseCertificateError> {
+        self.expect('(')?;
+        let mut items = vec![item(self)?];
+        while self.eat(',') {
+            items.push(item(self)?);
+        }
+        self.expect(')')?;
+        Ok(items)
+    }
+
+    fn certificate(&mut self) -> Result<Certificate, ParseCertificateError> {
+        let p = self.number()?;
+        if !self.eat('=') { return Ok(Certificate::Small(p)); }
+
+        if self.eat('P') {
+            let witness = self.number()?;
+            let factors = self.list(|parser| {
+                let c = parser.certificate()?;
+                parser.expect('^')?;
+                Ok((c, parser.number()?))
+            })?;
+            Ok(Certificate::Pratt { p, witness, factors })
+        } else if self.eat('L') {
+            let factors = self.list(|parser| {
+                let c = parser.certificate()?;
+                parser.expect('^')?;
+                let e = parser.number()?;
+                parser.expect('@')?;
+                Ok((c, e, parser.number()?))
+            })?;
+            Ok(Certificate::Pocklington { p, factors })
+        } else {
+            Err(self.error("expected P or L"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::prime::primes_from;
+
+    #[quickcheck]
+    fn certificates_verify_and_round_trip(start: u64) -> bool {
+        let p = primes_from(start as u128).next().unwrap();
+        let c = certify(p).unwrap();
+
+        c.prime() == p && c.verify() && c.to_string().parse::<Certificate>() == Ok(c)
+    }
+
+    #[test]
+    fn both_kinds_come_up() {
+        // 2^61 - 2 = 2 * 3^2 * 5^2 * 7 * 11 * 13 * 31 * 41 * 61 * 151 * 331 * 1321 has only small
+        // factors, where the p - 1 below is 2 * 1000000007 * 2000000137 and needs rho.
+        let mersenne = certify((1 << 61) - 1).unwrap();
+        let split = certify(4_000_000_302_000_001_919).unwrap();
+
+        assert!(matches!(mersenne, Certificate::Pocklington { .. }) && mersenne.verify());
+        assert!(matches!(split, Certificate::Pratt { .. }) && split.verify());
+        assert!(certify((1 << 89) - 1).unwrap().verify());
+    }
+
+    #[test]
+    fn composites_have_no_certificate() {
+        for n in [0, 1, 4, 2047, 3_215_031_751, ((1 << 61) - 1) * 1_000_003] {
+            assert_eq!(certify(n), None);
+        }
+    }
+
+    #[test]
+    fn forged_certificates_fail() {
+        let Some(Certificate::Pocklington { p, mut factors }) = certify((1 << 61) - 1) else { panic!() };
+
+        // 1000001 = 101 * 9901, and 1000000 = 2^6 * 5^6.
+        assert!(!Certificate::Small(1_000_001).verify());
+        assert!(!Certificate::Pratt {
+            p: 1_000_001,
+            witness: 2,
+            factors: vec![(Certificate::Small(2), 6), (Certificate::Small(5), 6)],
+        }.verify());
+
+        // A witness that doesn't witness anything, and a factor that isn't prime.
+        factors[0].2 = 1;
+        assert!(!Certificate::Pocklington { p, factors: factors.clone() }.verify());
+        factors[0] = (Certificate::Small(4), 1, 3);
+        assert!(!Certificate::Pocklington { p, factors }.verify());
+    }
+
+    #[test]
+    fn rejects_malformed_text() {
+        for s in ["", "7=", "7=P3", "7=P3(2^1", "7=X(2^1)", "7=L(2^1)", "7 "] {
+            assert!(s.parse::<Certificate>().is_err(), "{:?}", s);
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use crate::math::legendre::{jacobi, LegendreSymbol};
+use crate::math::mod_exp;
+use crate::math::sieve::Primes;
+use crate::math::square::is_perfect_square;
+use crate::montgomery::{Elt, ExpPlan, Space, Word};
+
+pub mod certificate;
+pub use certificate::{certify, Certificate};
+
+// IDEA: Implement a MR Prime Basis finder using GA.
+
+
+//
+const MR_BASES: [u128; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+//
+//
+
+/// The first `n` that passes Miller-Rabin for every base in `MR_BASES` without being prime
+/// (Sorenson & Webster, "Strong pseudoprimes to twelve prime bases", Math. Comp. 2017). Below it,
+/// those bases are a proof; from it on, `is_prime` switches to Baillie-PSW.
+pub const MR_DETERMINISTIC_BOUND: u128 = 318_665_857_834_031_151_167_461;
+
+/// Which test `is_prime` settles a given `n` with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Regime {
+    /// Miller-Rabin over `MR_BASES`, which is exact below `MR_DETERMINISTIC_BOUND`.
+    MillerRabin,
+    /// Strong base 2 Miller-Rabin followed by a strong Lucas test. No composite is known to pass
+    /// both, but unlike the regime below it, that isn't a proof.
+    BailliePsw,
+}
+
+#[inline] pub fn regime(n: u128) -> Regime {
+    if n < MR_DETERMINISTIC_BOUND { Regime::MillerRabin } else { Regime::BailliePsw }
+}
+
+// Miller-Rabin Primality test using MR_BASES as it's set of bases. Implemented following:
+// https://cp-algorithms.com/algebra/primality_tests.html
+//
+// The witness loop runs in a Montgomery space around `n`, in the narrowest word that holds it.
+// The space is a bare one, since its legendre constants would cost more to set up than a round of
+// the test. Every odd `n` fits in `Space<128>`, but should building the space fail anyway, we fall
+// back to `mod_exp`.
+//
+// From `MR_DETERMINISTIC_BOUND` on, those bases no longer prove anything, and `n` goes to
+// `baillie_psw` instead; `regime(n)` says which of the two a result came from.
+#[inline] pub fn is_prime(n: u128) -> bool {
+    if n < 2 { return false; }
+    if n == 2 { return true; }
+    if n & 1 == 0 { return false; }
+
+    if regime(n) == Regime::BailliePsw { return baillie_psw(n); }
+
+    let (d, r) = odd_part(n - 1);
+
+    let montgomery = if n >> 32 == 0 {
+        Space::<32, u32>::try_new_bare(n).map(|space| miller_rabin_in(&space, d, r, &MR_BASES))
+    } else if n >> 64 == 0 {
+        Space::<64, u64>::try_new_bare(n).map(|space| miller_rabin_in(&space, d, r, &MR_BASES))
+    } else {
+        Space::<128>::try_new_bare(n).map(|space| miller_rabin_in(&space, d, r, &MR_BASES))
+    };
+
+    montgomery.unwrap_or_else(|_| miller_rabin(n, d, r))
+}
+
+/// `(d, r)` with `m = d * 2^r` and `d` odd.
+#[inline] fn odd_part(m: u128) -> (u128, u128) {
+    let r = m.trailing_zeros();
+    (m >> r, r as u128)
+}
+
+/// The witness loop over `mod_exp`, for `n - 1 = d * 2^r`.
+fn miller_rabin(n: u128, d: u128, r: u128) -> bool {
+    for &a in MR_BASES.iter() {
+        if a == n { return true; }
+        if check_composite(n, a, d, r) { return false; }
+    }
+
+    true
+}
+
+/// The witness loop in Montgomery form. Every base is raised to the same `d`, so the window
+/// schedule for it is worked out once.
+fn miller_rabin_in<const R_EXP: usize, W: Word>(space: &Space<R_EXP, W>, d: u128, r: u128, bases: &[u128]) -> bool {
+    let n = space.n.to_u128();
+    let plan = ExpPlan::new(d);
+    let one = space.enter(1);
+    let minus_one = space.enter(n - 1);
+
+    for &a in bases.iter() {
+        if a == n { return true; }
+
+        let mut x = space.enter(a).exp_with(&plan);
+        if x == one || x == minus_one { continue; }
+
+        let mut witnessed = true;
+        for _ in 1..r {
+            x = x * x;
+            if x == minus_one {
+                witnessed = false;
+                break;
+            }
+        }
+        if witnessed { return false; }
+    }
+
+    true
+}
+
+/// The Baillie-PSW test, for odd `n` above every base in `MR_BASES`.
+///
+/// After trial division by `MR_BASES`, `n` has to be a strong probable prime to base 2 and a
+/// strong Lucas probable prime. The two kinds of pseudoprime fall in different places, and none
+/// has been found that is both.
+pub fn baillie_psw(n: u128) -> bool {
+    debug_assert!(n & 1 == 1 && n > MR_BASES[MR_BASES.len() - 1]);
+
+    if MR_BASES.iter().any(|&p| n.is_multiple_of(p)) { return false; }
+
+    let space = Space::<128>::new_bare(n);
+    let (d, r) = odd_part(n - 1);
+
+    miller_rabin_in(&space, d, r, &[2]) && strong_lucas(&space)
+}
+
+/// Selfridge's choice of Lucas parameters for `n`: the first `D` in 5, -7, 9, -11, ... with
+/// `(D/n) = -1`, and `P = 1`, `Q = (1 - D) / 4`. Returns `D`, or `None` if `n` turns out to be
+/// composite on the way (it shares a factor with some `D`, or is a square, for which no `D` will
+/// ever do).
+pub fn selfridge(n: u128) -> Option<i128> {
+    let mut d: i128 = 5;
+
+    loop {
+        let abs = d.unsigned_abs();
+        let symbol = if d > 0 { jacobi(abs, n) } else { jacobi(n - abs % n, n) };
+
+        match symbol {
+            LegendreSymbol::Nonresidue => return Some(d),
+            LegendreSymbol::Divisor if !abs.is_multiple_of(n) => return None,
+            _ => {}
+        }
+
+        // Squares are residues for every D, so test for one once the search has gone on a while.
+        if d == 13 && is_perfect_square(n) { return None; }
+
+        d = if d > 0 { -(d + 2) } else { -d + 2 };
+    }
+}
+
+/// `(U_k, V_k, Q^k)` for the Lucas sequences with `P = 1` and discriminant `D`, by the binary
+/// method: `U_2k = U_k V_k`, `V_2k = V_k^2 - 2Q^k`, and stepping `k` up by one with
+/// `U_k+1 = (U_k + V_k) / 2`, `V_k+1 = (D U_k + V_k) / 2`.
+pub fn lucas_sequence<'a>(space: &'a Space<128>, d: i128, k: u128) -> (Elt<'a, 128>, Elt<'a, 128>, Elt<'a, 128>) {
+    let n = space.n;
+    let signed = |x: i128| if x < 0 { space.enter(n - x.unsigned_abs() % n) } else { space.enter(x as u128) };
+    let big_d = signed(d);
+    let q = signed((1 - d) / 4);
+
+    let mut u = space.enter(0);
+    let mut v = space.enter(2);
+    let mut qk = space.enter(1);
+
+    for bit in (0..u128::BITS - k.leading_zeros()).rev() {
+        u *= v;
+        v = v * v - (qk + qk);
+        qk = qk * qk;
+
+        if (k >> bit) & 1 == 1 {
+            (u, v) = (half(u + v), half(big_d * u + v));
+            qk *= q;
+        }
+    }
+
+    (u, v, qk)
+}
+
+/// `x / 2`. Halving commutes with the factor of `R`, so it works on the Montgomery form as is.
+#[inline] fn half(x: Elt<'_, 128>) -> Elt<'_, 128> {
+    let val = if x.val & 1 == 0 { x.val >> 1 } else { (x.val >> 1) + (x.space.n >> 1) + 1 };
+    Elt { val, space: x.space }
+}
+
+/// The strong Lucas probable prime test with Selfridge's parameters. With `n + 1 = d * 2^s`, `n`
+/// passes if `U_d = 0`, or `V_(d 2^r) = 0` for some `r < s`.
+pub fn strong_lucas(space: &Space<128>) -> bool {
+    let n = space.n;
+    let Some(d) = selfridge(n) else { return false; };
+
+    // A factor of `Q` is a factor of `n` we'd otherwise miss.
+    let q = ((1 - d) / 4).unsigned_abs();
+    if q > 1 && n.is_multiple_of(q) && n != q { return false; }
+
+    let (k, s) = odd_part(n + 1);
+    let (u, mut v, mut qk) = lucas_sequence(space, d, k);
+
+    let zero = space.enter(0);
+    if u == zero || v == zero { return true; }
+
+    for _ in 1..s {
+        v = v * v - (qk + qk);
+        qk = qk * qk;
+        if v == zero { return true; }
+    }
+
+    false
+}
+
+#[inline] fn check_composite(n: u128, a: u128, d: u128, s: u128) -> bool {
+    let mut x = mod_exp(a,d,n);
+
+    if x == 1 || x == n - 1 { return false; }
+
+    for _ in 1..s {
+        x = mod_exp(x, 2, n);
+        if x == n - 1 { return false; }
+    }
+
+    true
+}
+
+/// The primes in `low..=high`.
+pub fn segmented_seive(low: u128, high: u128) -> Vec<u128> {
+    primes_from(low).take_while(|&p| p <= high).collect()
+}
+
+/// Every prime from `start` up: sieved below `SIEVE_LIMIT`, and tested with `is_prime` past it.
+/// The caller can add additional filters.
+pub fn primes_from(start: u128) -> Primes {
+    Primes::new(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::montgomery::test_case::WideTestCase;
+
+    const SMALL_PRIMES : [u128; 168] = [
+        2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+        97, 101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181,
+        191, 193, 197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281,
+        283, 293, 307, 311, 313, 317, 331, 337, 347, 349, 353, 359, 367, 373, 379, 383, 389, 397,
+        401, 409, 419, 421, 431, 433, 439, 443, 449, 457, 461, 463, 467, 479, 487, 491, 499, 503,
+        509, 521, 523, 541, 547, 557, 563, 569, 571, 577, 587, 593, 599, 601, 607, 613, 617, 619,
+        631, 641, 643, 647, 653, 659, 661, 673, 677, 683, 691, 701, 709, 719, 727, 733, 739, 743,
+        751, 757, 761, 769, 773, 787, 797, 809, 811, 821, 823, 827, 829, 839, 853, 857, 859, 863,
+        877, 881, 883, 887, 907, 911, 919, 929, 937, 941, 947, 953, 967, 971, 977, 983, 991, 997
+    ];
+
+    fn naive_is_prime(a: u128) -> bool {
+        if a < 2 { return false; }
+        for p in SMALL_PRIMES.iter() {
+            if a == *p { return true; }
+            if a.is_multiple_of(*p) { return false; }
+        }
+        true
+    }
+
+    #[test]
+    fn primes_from_works() {
+        let primes : Vec<u128> = primes_from(2).take(168).collect();
+        assert_eq!(primes, SMALL_PRIMES);
+    }
+
+    #[quickcheck]
+    fn miller_rabin_correctness(a_in: u16) -> bool {
+        let a = a_in as u128; // keep the size small by restricting to u16, but we need to work on
+                              // u128s
+        naive_is_prime(a) == is_prime(a)
+    }
+
+    #[quickcheck]
+    fn montgomery_miller_rabin_is_mod_exp_miller_rabin(a_in: u16) -> bool {
+        let n = a_in as u128 | 1;
+        if n < 3 { return true; }
+
+        let (d, r) = odd_part(n - 1);
+        is_prime(n) == miller_rabin(n, d, r)
+    }
+
+    #[quickcheck]
+    fn wide_montgomery_miller_rabin_is_mod_exp_miller_rabin(tc: WideTestCase, shift: u8) -> bool {
+        let n = ((tc.n >> (shift % 126)) % MR_DETERMINISTIC_BOUND) | 1;
+        if n < 3 { return true; }
+
+        let (d, r) = odd_part(n - 1);
+        is_prime(n) == miller_rabin(n, d, r)
+    }
+
+    #[test]
+    fn known_primes_across_words() {
+        // Straddling each word boundary, and the Mersenne primes, which make for awkward moduli.
+        for p in [4_294_967_291, 4_294_967_311, 18_446_744_073_709_551_557, 18_446_744_073_709_551_629,
+                  (1 << 61) - 1, (1 << 89) - 1, (1 << 107) - 1, (1 << 127) - 1] {
+            assert!(is_prime(p), "{}", p);
+        }
+        for c in [4_294_967_291 * 4_294_967_311, (1 << 67) - 1, 3_215_031_751, 3_825_123_056_546_413_051] {
+            assert!(!is_prime(c), "{}", c);
+        }
+    }
+
+    #[quickcheck]
+    fn baillie_psw_agrees_with_miller_rabin_below_the_bound(tc: WideTestCase, shift: u8) -> bool {
+        let n = ((tc.n >> (shift % 126)) % MR_DETERMINISTIC_BOUND) | 1;
+        if n <= 37 { return true; }
+
+        baillie_psw(n) == is_prime(n)
+    }
+
+    #[quickcheck]
+    fn lucas_sequence_matches_the_recurrence(k: u8, which: u8) -> bool {
+        // U_k+2 = U_k+1 - Q U_k and V_k+2 = V_k+1 - Q V_k, with P = 1.
+        let n = primes_from(41).nth(which as usize).unwrap();
+        let space = Space::<128>::new(n);
+        let Some(d) = selfridge(n) else { return false; };
+        let q = (1 - d) / 4;
+        let q = if q < 0 { space.enter(n - q.unsigned_abs()) } else { space.enter(q as u128) };
+
+        let (mut u0, mut u1) = (space.enter(0), space.enter(1));
+        let (mut v0, mut v1) = (space.enter(2), space.enter(1));
+        for _ in 0..k {
+            (u0, u1) = (u1, u1 - q * u0);
+            (v0, v1) = (v1, v1 - q * v0);
+        }
+
+        let (u, v, _) = lucas_sequence(&space, d, k as u128);
+        u == u0 && v == v0
+    }
+
+    #[test]
+    fn the_two_halves_of_baillie_psw_catch_each_others_pseudoprimes() {
+        // Strong pseudoprimes to base 2, which the Lucas half rejects ...
+        for n in [2047, 3277, 4033, 4681, 8321, 3_215_031_751] {
+            assert!(miller_rabin_in(&Space::<128>::new(n), odd_part(n - 1).0, odd_part(n - 1).1, &[2]));
+            assert!(!strong_lucas(&Space::<128>::new(n)), "{}", n);
+        }
+        // ... and strong Lucas pseudoprimes, which base 2 rejects.
+        for n in [5459, 5777, 10877, 16109, 18971] {
+            assert!(strong_lucas(&Space::<128>::new(n)), "{}", n);
+            assert!(!baillie_psw(n), "{}", n);
+        }
+    }
+
+    #[test]
+    fn baillie_psw_above_the_bound() {
+        // The bound itself, and the next such number for the first 13 primes, fool every base in
+        // MR_BASES.
+        for c in [MR_DETERMINISTIC_BOUND, 3_317_044_064_679_887_385_961_981] {
+            let (d, r) = odd_part(c - 1);
+            assert!(miller_rabin(c, d, r));
+            assert!(!is_prime(c), "{}", c);
+        }
+
+        for p in [(1 << 89) - 1, (1 << 107) - 1, (1 << 127) - 1] {
+            assert_eq!(regime(p), Regime::BailliePsw);
+            assert!(is_prime(p), "{}", p);
+        }
+        assert!(!is_prime(((1 << 61) - 1) * 18_446_744_073_709_551_557));
+        assert!(!is_prime(18_446_744_073_709_551_557u128.pow(2)));
+    }
+
+    #[test]
+    fn segmented_seive_finds_small_primes() {
+        let primes = segmented_seive(0, 1000);
+        for p in SMALL_PRIMES.iter() {
+            assert!(primes.contains(p));
+        }
+    }
+
+    #[test]
+    fn miller_rabin_small_primes() {
+        for p in SMALL_PRIMES.iter() {
+            assert!(is_prime(*p));
+        }
+    }
+}
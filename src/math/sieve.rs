@@ -0,0 +1,196 @@
+use crate::math::prime::is_prime;
+use std::sync::OnceLock;
+
+/// The residues mod 30 that are coprime to 2, 3 and 5. A byte of the sieve covers 30 integers,
+/// with a bit for each of these, so only 8 in 30 integers are ever stored.
+const WHEEL: [u8; 8] = [1, 7, 11, 13, 17, 19, 23, 29];
+
+/// The bit for each residue mod 30, or 8 for residues the wheel skips.
+const WHEEL_BIT: [u8; 30] = [
+    8, 0, 8, 8, 8, 8, 8, 1, 8, 8, 8, 2, 8, 3, 8, 8, 8, 4, 8, 5, 8, 8, 8, 6, 8, 8, 8, 8, 8, 7,
+];
+
+/// The inverse mod 30 of each residue in `WHEEL`.
+const WHEEL_INVERSE: [u8; 8] = [1, 13, 11, 7, 23, 19, 17, 29];
+
+/// Bytes in a segment. Small enough to stay in L1, and the broker's usual request of a few dozen
+/// primes is served from the first one.
+pub const SEGMENT_BYTES: usize = 1 << 12;
+
+/// Integers covered by a segment.
+pub const SEGMENT_SPAN: u128 = SEGMENT_BYTES as u128 * 30;
+
+/// Where `Primes` stops sieving and starts testing wheel candidates with `is_prime`. Past here,
+/// each segment spends longer placing its tens of thousands of base primes than it would take to
+/// test the few candidates a caller usually wants.
+pub const SIEVE_LIMIT: u128 = 1 << 36;
+
+/// The base primes, from 7 up to the square root of the last segment below `SIEVE_LIMIT`. They're
+/// sieved once, on first use, and shared.
+fn base_primes() -> &'static [u32] {
+    static BASE: OnceLock<Vec<u32>> = OnceLock::new();
+
+    BASE.get_or_init(|| {
+        let limit = crate::math::square::isqrt(SIEVE_LIMIT + SEGMENT_SPAN) as usize + 1;
+        let mut composite = vec![false; limit + 1];
+        let mut primes = vec![];
+
+        for p in 2..=limit {
+            if composite[p] { continue; }
+            if p >= 7 { primes.push(p as u32); }
+
+            for m in (p * p..=limit).step_by(p) {
+                composite[m] = true;
+            }
+        }
+
+        primes
+    })
+}
+
+/// Every prime from `start` up, in order.
+///
+/// Below `SIEVE_LIMIT` this is a segmented sieve of Eratosthenes over a 2/3/5 wheel. It holds
+/// one segment of `SEGMENT_BYTES` and the primes found in it, beside the shared base primes.
+/// Past the limit, it walks the wheel and asks `is_prime`.
+#[derive(Debug, Clone)]
+pub struct Primes {
+    start: u128,
+    /// The start of the next segment to sieve, or the next candidate once past `SIEVE_LIMIT`.
+    next: u128,
+    segment: Vec<u8>,
+    found: Vec<u128>,
+    position: usize,
+}
+
+impl Primes {
+    pub fn new(start: u128) -> Primes {
+        let found = [2, 3, 5].into_iter().filter(|&p| p >= start).collect();
+        let next = if start < SIEVE_LIMIT { start - start % 30 } else { start };
+
+        Primes { start, next, segment: vec![], found, position: 0 }
+    }
+
+    /// Sieves the segment at `self.next` into `self.found`. Everything here is below
+    /// `SIEVE_LIMIT`, so the arithmetic is done in `u64`.
+    fn sieve_segment(&mut self) {
+        let lo = self.next as u64;
+        let hi = lo + SEGMENT_SPAN as u64;
+
+        self.segment.clear();
+        self.segment.resize(SEGMENT_BYTES, 0xff);
+        if lo == 0 { self.segment[0] &= !1; }
+
+        for &p in base_primes() {
+            let p = p as u64;
+            if p * p >= hi { break; }
+
+            // The multiples p * k left on the wheel are those with k on it too. For each residue r,
+            // find the first such k with p * k = r mod 30 at or past both p^2 and the segment; from
+            // there, every 30th k (every p bytes) is on the same bit.
+            let k0 = (p * p).max(lo).div_ceil(p);
+            let inverse = WHEEL_INVERSE[WHEEL_BIT[(p % 30) as usize] as usize] as u64;
+            let k0_residue = k0 % 30;
+
+            for (bit, &r) in WHEEL.iter().enumerate() {
+                let c = r as u64 * inverse % 30;
+                let k = k0 + (c + 30 - k0_residue) % 30;
+
+                let mut i = ((p * k - lo) / 30) as usize;
+                while i < SEGMENT_BYTES {
+                    self.segment[i] &= !(1 << bit);
+                    i += p as usize;
+                }
+            }
+        }
+
+        self.found.clear();
+        self.position = 0;
+        for (i, &byte) in self.segment.iter().enumerate() {
+            let mut byte = byte;
+            while byte != 0 {
+                let bit = byte.trailing_zeros();
+                byte &= byte - 1;
+
+                let n = (lo + 30 * i as u64 + WHEEL[bit as usize] as u64) as u128;
+                if n >= self.start { self.found.push(n); }
+            }
+        }
+
+        self.next = hi as u128;
+    }
+
+    /// The next wheel candidate past `SIEVE_LIMIT` that `is_prime` accepts.
+    fn next_tested(&mut self) -> Option<u128> {
+        loop {
+            let n = self.next;
+            self.next = n.checked_add(1)?;
+
+            if WHEEL_BIT[(n % 30) as usize] != 8 && is_prime(n) { return Some(n); }
+        }
+    }
+}
+
+impl Iterator for Primes {
+    type Item = u128;
+
+    fn next(&mut self) -> Option<u128> {
+        loop {
+            if let Some(&p) = self.found.get(self.position) {
+                self.position += 1;
+                return Some(p);
+            }
+
+            if self.next >= SIEVE_LIMIT { return self.next_tested(); }
+
+            self.sieve_segment();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[quickcheck]
+    fn sieve_matches_is_prime(start: u32, len: u16) -> bool {
+        let start = start as u128;
+        let end = start + len as u128;
+
+        let sieved : Vec<u128> = Primes::new(start).take_while(|&p| p < end).collect();
+        let tested : Vec<u128> = (start..end).filter(|&n| is_prime(n)).collect();
+
+        sieved == tested
+    }
+
+    #[test]
+    fn counts_the_primes_below_a_million() {
+        // Enough to cross several segments.
+        assert_eq!(Primes::new(0).take_while(|&p| p < 1_000_000).count(), 78_498);
+    }
+
+    #[test]
+    fn crosses_segment_boundaries() {
+        for start in [SEGMENT_SPAN - 100, 7 * SEGMENT_SPAN - 100, 1_000_000_007 * 30] {
+            let sieved : Vec<u128> = Primes::new(start).take_while(|&p| p < start + 200).collect();
+            let tested : Vec<u128> = (start..start + 200).filter(|&n| is_prime(n)).collect();
+            assert_eq!(sieved, tested);
+        }
+    }
+
+    #[test]
+    fn hands_over_to_is_prime_past_the_limit() {
+        let start = SIEVE_LIMIT - 3_000;
+
+        let sieved : Vec<u128> = Primes::new(start).take(200).collect();
+        let tested : Vec<u128> = (start..).filter(|&n| is_prime(n)).take(200).collect();
+
+        assert!(sieved.last().unwrap() > &SIEVE_LIMIT);
+        assert_eq!(sieved, tested);
+    }
+
+    #[test]
+    fn stops_at_the_top_of_the_range() {
+        assert_eq!(Primes::new(u128::MAX - 200).last(), Some(u128::MAX - 158));
+    }
+}
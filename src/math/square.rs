@@ -0,0 +1,160 @@
+use crate::math::bigint::BigUint;
+
+/// Which residues mod `M` are squares.
+const fn squares<const M: usize>() -> [bool; M] {
+    let mut table = [false; M];
+    let mut i = 0;
+    while i < M {
+        table[i * i % M] = true;
+        i += 1;
+    }
+    table
+}
+
+static SQUARES_64: [bool; 64] = squares();
+static SQUARES_63: [bool; 63] = squares();
+static SQUARES_65: [bool; 65] = squares();
+static SQUARES_11: [bool; 11] = squares();
+
+/// Whether `n` can be a square, given `n mod 64` and `n mod 63 * 65 * 11`. Only 12 of the 64
+/// classes mod 64 are squares, 16 of 63, 21 of 65 and 6 of 11, so fewer than one non-square in a
+/// hundred gets through to the square root.
+fn might_be_square(mod_64: u64, mod_45045: u64) -> bool {
+    SQUARES_64[mod_64 as usize]
+        && SQUARES_63[(mod_45045 % 63) as usize]
+        && SQUARES_65[(mod_45045 % 65) as usize]
+        && SQUARES_11[(mod_45045 % 11) as usize]
+}
+
+/// `floor(sqrt(n))`, exactly.
+///
+/// Newton's iteration `x -> (x + n/x) / 2` comes down on the root from above and stops at it. One
+/// step from any positive guess is an overestimate, and from the float estimate it's already
+/// within a few, so this is two or three divisions.
+pub fn isqrt(n: u128) -> u128 {
+    if n < 2 { return n; }
+
+    let guess = ((n as f64).sqrt() as u128).max(1);
+    let mut x = (guess + n / guess) / 2;
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x { return x; }
+        x = y;
+    }
+}
+
+/// The square root of `n`, if it's a perfect square.
+pub fn exact_sqrt(n: u128) -> Option<u128> {
+    if !might_be_square(n as u64 & 63, (n % 45045) as u64) { return None; }
+
+    let root = isqrt(n);
+    (root * root == n).then_some(root)
+}
+
+pub fn is_perfect_square(n: u128) -> bool {
+    exact_sqrt(n).is_some()
+}
+
+impl BigUint {
+    /// `floor(sqrt(self))`, exactly, by the same Newton iteration as `isqrt`. The start is the root
+    /// of the top 128 or so bits, rounded up and shifted back, which is above the root and gets
+    /// the first 64 bits right.
+    pub fn isqrt(&self) -> BigUint {
+        if let Some(n) = self.to_u128() { return BigUint::from(isqrt(n)); }
+
+        let shift = (self.bits() - 127) / 2;
+        let top = (self >> (2 * shift)).to_u128().unwrap();
+        let mut x = &BigUint::from(isqrt(top) + 1) << shift;
+        loop {
+            let y = &(&x + &self.div_rem(&x).0) >> 1;
+            if y >= x { return x; }
+            x = y;
+        }
+    }
+
+    /// The square root, if this is a perfect square.
+    pub fn exact_sqrt(&self) -> Option<BigUint> {
+        if !might_be_square(self.low_u64() & 63, self.rem_u128(45045) as u64) { return None; }
+
+        let root = self.isqrt();
+        (&root * &root == *self).then_some(root)
+    }
+
+    pub fn is_perfect_square(&self) -> bool {
+        self.exact_sqrt().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::montgomery::test_case::WideTestCase;
+
+    #[quickcheck]
+    fn isqrt_is_the_floor(case: WideTestCase) -> bool {
+        [case.a, case.n, case.a >> 64, case.b >> 17].iter().all(|&n| {
+            let r = isqrt(n);
+            r * r <= n && (r + 1).checked_mul(r + 1).is_none_or(|s| s > n)
+        })
+    }
+
+    #[quickcheck]
+    fn squares_and_their_neighbours(case: WideTestCase) -> bool {
+        // Below 2^63, so the neighbours of the square fit too.
+        let x = case.a >> 65;
+        let square = x * x;
+
+        exact_sqrt(square) == Some(x)
+            && (x < 2 || !is_perfect_square(square - 1))
+            && !is_perfect_square(square + 2 * x + 2)
+    }
+
+    #[quickcheck]
+    fn big_isqrt_is_the_floor(a: u128, b: u128, c: u128) -> bool {
+        let n = &(&(&BigUint::from(a) * &BigUint::from(b)) * c) + 1;
+        let r = n.isqrt();
+        let r1 = &r + 1;
+
+        &r * &r <= n && &r1 * &r1 > n
+    }
+
+    #[quickcheck]
+    fn big_squares_and_their_neighbours(a: u128, b: u128) -> bool {
+        let x = &(&BigUint::from(a) * &BigUint::from(b)) + 2;
+        let square = &x * &x;
+
+        square.exact_sqrt() == Some(x.clone())
+            && !(&square + 1).is_perfect_square()
+            && !(&square + &(&x << 1)).is_perfect_square()
+    }
+
+    #[test]
+    fn edges() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(3), 1);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+        assert_eq!(exact_sqrt((u64::MAX as u128).pow(2)), Some(u64::MAX as u128));
+        assert!(is_perfect_square(0) && is_perfect_square(1) && !is_perfect_square(u128::MAX));
+
+        let two_128 = &BigUint::from(u128::MAX) + 1;
+        assert_eq!(two_128.exact_sqrt(), Some(BigUint::from(1 << 64)));
+        assert_eq!((&(&two_128 * &two_128) + &BigUint::from(u128::MAX)).isqrt(), two_128);
+    }
+
+    #[test]
+    fn brocard_solutions_are_squares() {
+        // 4! + 1 = 5^2, 5! + 1 = 11^2, 7! + 1 = 71^2, and 6! + 1 = 721 = 7 * 103.
+        assert_eq!(exact_sqrt(25), Some(5));
+        assert_eq!(exact_sqrt(121), Some(11));
+        assert_eq!(exact_sqrt(5041), Some(71));
+        assert_eq!(exact_sqrt(721), None);
+    }
+
+    #[test]
+    fn prefilter_passes_every_square() {
+        for x in 0..45045 * 64u64 {
+            let square = x * x;
+            assert!(might_be_square(square & 63, square % 45045), "{}", x);
+        }
+    }
+}
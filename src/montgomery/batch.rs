@@ -0,0 +1,190 @@
+use crate::math::legendre::{jacobi, LegendreSymbol};
+use crate::montgomery::{ExpPlan, Space, SpaceError, Word};
+
+/// A structure-of-arrays view over many Montgomery spaces at once.
+///
+/// `BrocardSpan` wants to hold `n!` in every one of its spaces and push them all forward together.
+/// A `Vec<Elt>` does that, but each element drags a pointer to its `Space` along with it, and the
+/// constants it needs are scattered across however many `Space`s were allocated. Here every
+/// constant lives in its own contiguous array, indexed by lane, and every operation is a straight
+/// loop over the lanes with no early exits. That's the shape the autovectorizer (or a later
+/// `std::simd` port) wants to see.
+///
+/// The residues are kept in Montgomery form throughout, exactly as an `Elt` would hold them.
+#[derive(Debug, Clone)]
+pub struct SpaceBatch<const R_EXP: usize, W: Word = u128> {
+    pub moduli: Vec<W>,
+    pub n_primes: Vec<W>,
+    pub r_squareds: Vec<W>,
+    /// `1` entered into each space, i.e. `R mod n_i`.
+    pub ones: Vec<W>,
+    /// The sliding-window plan for each lane's Euler criterion exponent `(n_i - 1) / 2`, as its
+    /// `Space` would cache it.
+    pub plans: Vec<ExpPlan>,
+    pub r_n_legendres: Vec<LegendreSymbol>,
+    pub residues: Vec<W>,
+    /// The multiplier `advance` steps through, entered into each space. Starts at `0`.
+    pub counters: Vec<W>,
+}
+
+impl<const R_EXP: usize, W: Word> SpaceBatch<R_EXP, W> {
+    /// Builds a batch with one lane per modulus, with every residue set to `1`.
+    ///
+    /// Panics if any modulus isn't valid for a `Space<R_EXP, W>`; see `try_new`.
+    pub fn new(moduli: &[u128]) -> SpaceBatch<R_EXP, W> {
+        Self::try_new(moduli).unwrap_or_else(|e| panic!("invalid montgomery space: {}", e))
+    }
+
+    /// Builds the batch, or reports the first modulus that can't make a `Space<R_EXP, W>`.
+    pub fn try_new(moduli: &[u128]) -> Result<SpaceBatch<R_EXP, W>, SpaceError> {
+        let spaces : Vec<Space<R_EXP, W>> = moduli.iter().map(|&n| Space::try_new(n)).collect::<Result<_, _>>()?;
+        let ones : Vec<W> = spaces.iter().map(|s| s.enter(1).val).collect();
+
+        Ok(SpaceBatch {
+            moduli: spaces.iter().map(|s| s.n).collect(),
+            n_primes: spaces.iter().map(|s| s.n_prime).collect(),
+            r_squareds: spaces.iter().map(|s| s.r_squared).collect(),
+            // `try_new` always leaves a plan.
+            plans: spaces.iter().map(|s| s.legendre_plan.clone().unwrap()).collect(),
+            r_n_legendres: spaces.iter().map(|s| s.r_n_legendre).collect(),
+            residues: ones.clone(),
+            counters: vec![W::ZERO; ones.len()],
+            ones,
+        })
+    }
+
+    #[inline] pub fn len(&self) -> usize {
+        self.moduli.len()
+    }
+
+    #[inline] pub fn is_empty(&self) -> bool {
+        self.moduli.is_empty()
+    }
+
+    /// Sets every residue to `n!` in its space.
+    pub fn set_factorial(&mut self, n: u128) {
+        self.residues.copy_from_slice(&self.ones);
+        self.set_counter(0);
+        for _ in 0..n {
+            self.advance();
+        }
+    }
+
+    /// Sets every residue to `n!` using `Space::prime_factorial`, so each lane picks whichever
+    /// of the forward product or Wilson reflection is cheaper for its modulus. Every modulus must
+    /// be prime. The factorial needs no legendre symbols, so each lane gets a bare space.
+    pub fn set_prime_factorial(&mut self, n: u128) {
+        for (i, v) in self.residues.iter_mut().enumerate() {
+            let space = Space::<R_EXP, W>::new_bare(self.moduli[i].to_u128());
+            *v = space.prime_factorial(n).val;
+        }
+    }
+
+    /// Sets every lane's counter to the plain integer `k`. This is the only place the counters
+    /// are entered, at the cost of a reduction of `k` mod each modulus and a REDC per lane.
+    pub fn set_counter(&mut self, k: u128) {
+        for ((c, &n), (&n_prime, &r_squared)) in self.counters.iter_mut()
+            .zip(&self.moduli)
+            .zip(self.n_primes.iter().zip(&self.r_squareds)) {
+            let k = W::from_u128(k % n.to_u128());
+            *c = Space::<R_EXP, W>::mul_with(k, r_squared, n, n_prime);
+        }
+    }
+
+    /// Adds `1` to every counter and multiplies the residue by it, so a batch holding `k!` with
+    /// its counters at `k` moves on to `(k+1)!` and `k+1`. That's a modular add and one
+    /// multiply per lane, with nothing to enter.
+    #[inline] pub fn advance(&mut self) {
+        for (((v, c), &one), (&n, &n_prime)) in self.residues.iter_mut()
+            .zip(self.counters.iter_mut())
+            .zip(&self.ones)
+            .zip(self.moduli.iter().zip(&self.n_primes)) {
+            *c = Space::<R_EXP, W>::add_with(*c, one, n);
+            *v = Space::<R_EXP, W>::mul_with(*v, *c, n, n_prime);
+        }
+    }
+
+    /// Multiplies every residue by the plain (not Montgomery form) integer `k`.
+    ///
+    /// `k` is entered into each space as part of the multiply, which costs a reduction of `k` mod
+    /// each modulus and an extra REDC per lane. Walking through consecutive multipliers is what
+    /// `advance` is for.
+    #[inline] pub fn mul_scalar(&mut self, k: u128) {
+        for (((v, &n), &n_prime), &r_squared) in self.residues.iter_mut()
+            .zip(&self.moduli)
+            .zip(&self.n_primes)
+            .zip(&self.r_squareds) {
+            let k = W::from_u128(k % n.to_u128());
+            let k = Space::<R_EXP, W>::mul_with(k, r_squared, n, n_prime);
+            *v = Space::<R_EXP, W>::mul_with(*v, k, n, n_prime);
+        }
+    }
+
+    /// Adds `1` to every residue.
+    #[inline] pub fn add_one(&mut self) {
+        for ((v, &one), &n) in self.residues.iter_mut().zip(&self.ones).zip(&self.moduli) {
+            *v = Space::<R_EXP, W>::add_with(*v, one, n);
+        }
+    }
+
+    /// Subtracts `1` from every residue, undoing `add_one`.
+    #[inline] pub fn sub_one(&mut self) {
+        for ((v, &one), &n) in self.residues.iter_mut().zip(&self.ones).zip(&self.moduli) {
+            *v = Space::<R_EXP, W>::sub_with(*v, one, n);
+        }
+    }
+
+    /// Writes the legendre symbol of every residue into `out`, which must have one slot per lane.
+    ///
+    /// This is the same Euler criterion as `Space::legendre`, with each lane's cached sliding-window
+    /// plan. The lanes have different exponents, so their windows fall in different places and
+    /// they can't be stepped together; each lane runs its own plan to the end instead. That's
+    /// about 1.2 multiplies per exponent bit rather than the 2 of stepping every lane through a
+    /// shared binary ladder, which is more than the uniform loop ever won back.
+    pub fn legendre_all(&self, out: &mut [LegendreSymbol]) {
+        assert_eq!(out.len(), self.len(), "need one output slot per lane");
+
+        for (i, sym) in out.iter_mut().enumerate() {
+            *sym = self.legendre(i);
+        }
+    }
+
+    /// The legendre symbol of the residue in lane `i` alone, for callers that stop at the first
+    /// nonresidue rather than filling in every lane.
+    #[inline] pub fn legendre(&self, i: usize) -> LegendreSymbol {
+        let (n, n_prime) = (self.moduli[i], self.n_primes[i]);
+        let acc = Space::<R_EXP, W>::exp_with_plan(self.residues[i], &self.plans[i], n, n_prime);
+
+        let a_r_sym = if acc == W::ZERO {
+            LegendreSymbol::Divisor
+        } else if acc == self.ones[i] {
+            LegendreSymbol::Residue
+        } else {
+            LegendreSymbol::Nonresidue
+        };
+
+        a_r_sym * self.r_n_legendres[i]
+    }
+
+    /// Writes the legendre symbol of every residue into `out` using the binary Jacobi algorithm
+    /// instead of Euler's criterion. The algorithm is branchy and data-dependent, so this is a
+    /// plain loop over the lanes rather than anything the vectorizer can help with.
+    pub fn jacobi_all(&self, out: &mut [LegendreSymbol]) {
+        assert_eq!(out.len(), self.len(), "need one output slot per lane");
+
+        for (i, sym) in out.iter_mut().enumerate() {
+            *sym = self.jacobi(i);
+        }
+    }
+
+    /// `jacobi_all` for lane `i` alone.
+    #[inline] pub fn jacobi(&self, i: usize) -> LegendreSymbol {
+        jacobi(self.exit(i), self.moduli[i].to_u128())
+    }
+
+    /// The residue in lane `i`, out of Montgomery form.
+    #[inline] pub fn exit(&self, i: usize) -> u128 {
+        let n = self.moduli[i];
+        (Space::<R_EXP, W>::redc_with(self.residues[i], W::ZERO, n, self.n_primes[i]) % n).to_u128()
+    }
+}
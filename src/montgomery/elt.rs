@@ -1,25 +1,25 @@
-use crate::montgomery::Space;
-use crate::math::legendre::LegendreSymbol;
-use std::ops::{Add, Sub, Mul, MulAssign, AddAssign};
+use crate::montgomery::{Space, Word};
+use crate::math::{binary_mod_inverse, legendre::LegendreSymbol};
+use std::ops::{Add, Sub, Mul, Div, MulAssign, AddAssign};
 
 #[derive(Debug, Clone, Copy)]
-pub struct Elt<'a, const R_EXP: usize> {
-    pub val: u128,
-    pub space: &'a Space<R_EXP>
+pub struct Elt<'a, const R_EXP: usize, W: Word = u128> {
+    pub val: W,
+    pub space: &'a Space<R_EXP, W>
 }
 
-impl<const R_EXP: usize> Elt<'_, R_EXP> {
+impl<'a, const R_EXP: usize, W: Word> Elt<'a, R_EXP, W> {
     #[inline] pub fn exit(&self) -> u128 {
-        self.space.redc(self.val) % self.space.n
+        (self.space.redc(self.val) % self.space.n).to_u128()
     }
 
-    #[inline] pub fn exp(&self, e: u128) -> Elt<R_EXP> {
+    #[inline] pub fn exp(&self, e: u128) -> Elt<'a, R_EXP, W> {
         let mut val = self.space.enter(1);
-        let mut base = self.clone();
+        let mut base = *self;
         let mut exp = e;
 
         while exp > 0 {
-            if exp & 1 == 1 { val = val * base; }
+            if exp & 1 == 1 { val *= base; }
             base = base * base;
             exp >>= 1;
         }
@@ -27,80 +27,120 @@ impl<const R_EXP: usize> Elt<'_, R_EXP> {
         val
     }
 
+    /// The multiplicative inverse, or `None` if this isn't a unit mod `n`.
+    ///
+    /// Inverting the Montgomery form `aR` directly gives `a^-1 R^-1`, which is two factors of `R`
+    /// short of the `a^-1 R` we want. Each multiply by `R^2` (in the space, so `* R^2 * R^-1`)
+    /// recovers one of them.
+    pub fn inverse(&self) -> Option<Elt<'a, R_EXP, W>> {
+        let inv = binary_mod_inverse(self.val.to_u128(), self.space.n.to_u128())?;
+        let inv = W::from_u128(inv);
+
+        let val = self.space.mul(self.space.mul(inv, self.space.r_squared), self.space.r_squared);
+
+        Some(Elt { val, space: self.space })
+    }
+
     #[inline] pub fn legendre(&self) -> LegendreSymbol {
         self.space.legendre(*self)
     }
+
+    #[inline] pub fn jacobi(&self) -> LegendreSymbol {
+        self.space.jacobi(*self)
+    }
 }
 
-impl<const R_EXP: usize> PartialEq for Elt<'_, R_EXP> {
-    #[inline] fn eq(&self, other: &Elt<R_EXP>) -> bool {
+impl<const R_EXP: usize, W: Word> PartialEq for Elt<'_, R_EXP, W> {
+    #[inline] fn eq(&self, other: &Elt<R_EXP, W>) -> bool {
         self.val == other.val && self.space == other.space
     }
 }
 
-impl<const R_EXP: usize> AddAssign for Elt<'_, R_EXP> {
-    #[inline] fn add_assign(&mut self, other: Elt<R_EXP>) {
-        self.val = (self.val + other.val) % self.space.n;
+impl<const R_EXP: usize, W: Word> AddAssign for Elt<'_, R_EXP, W> {
+    #[inline] fn add_assign(&mut self, other: Elt<R_EXP, W>) {
+        self.val = self.space.add(self.val, other.val);
     }
 }
 
-impl<const R_EXP: usize> MulAssign for Elt<'_, R_EXP> {
-    #[inline] fn mul_assign(&mut self, other: Elt<R_EXP>) {
-        self.val = self.space.redc(self.val.wrapping_mul(other.val));
+impl<const R_EXP: usize, W: Word> MulAssign for Elt<'_, R_EXP, W> {
+    #[inline] fn mul_assign(&mut self, other: Elt<R_EXP, W>) {
+        self.val = self.space.mul(self.val, other.val);
     }
 }
-impl<const R_EXP: usize> MulAssign<u128> for Elt<'_, R_EXP> {
+impl<const R_EXP: usize, W: Word> MulAssign<u128> for Elt<'_, R_EXP, W> {
     #[inline] fn mul_assign(&mut self, other: u128) {
         *self *= self.space.enter(other);
     }
 }
 
-impl<'a, const R_EXP: usize> Add<Elt<'a, R_EXP>> for Elt<'a, R_EXP> {
-    type Output = Elt<'a, R_EXP>;
+impl<'a, const R_EXP: usize, W: Word> Add<Elt<'a, R_EXP, W>> for Elt<'a, R_EXP, W> {
+    type Output = Elt<'a, R_EXP, W>;
 
-    #[inline] fn add(self, other: Elt<'a, R_EXP>) -> Elt<'a, R_EXP> {
+    #[inline] fn add(self, other: Elt<'a, R_EXP, W>) -> Elt<'a, R_EXP, W> {
         Elt {
-            val: self.val.wrapping_add(other.val),
+            val: self.space.add(self.val, other.val),
             space: self.space
         }
     }
 }
 
-impl<'a, const R_EXP: usize> Add<u128> for Elt<'a, R_EXP> {
-    type Output = Elt<'a, R_EXP>;
+impl<'a, const R_EXP: usize, W: Word> Add<u128> for Elt<'a, R_EXP, W> {
+    type Output = Elt<'a, R_EXP, W>;
 
-    #[inline] fn add(self, other: u128) -> Elt<'a, R_EXP> {
+    #[inline] fn add(self, other: u128) -> Elt<'a, R_EXP, W> {
         self + self.space.enter(other)
     }
 
 }
 
-impl<'a, const R_EXP: usize> Mul for Elt<'a, R_EXP> {
-    type Output = Elt<'a, R_EXP>;
+impl<'a, const R_EXP: usize, W: Word> Mul for Elt<'a, R_EXP, W> {
+    type Output = Elt<'a, R_EXP, W>;
 
-    #[inline] fn mul(self, other: Elt<'a, R_EXP>) -> Elt<'a, R_EXP> {
+    #[inline] fn mul(self, other: Elt<'a, R_EXP, W>) -> Elt<'a, R_EXP, W> {
         Elt {
-            val: self.space.redc(self.val.wrapping_mul(other.val)),
+            val: self.space.mul(self.val, other.val),
             space: self.space
         }
     }
 }
 
-impl<'a, const R_EXP: usize> Mul<u128> for Elt<'a, R_EXP> {
-    type Output = Elt<'a, R_EXP>;
+impl<'a, const R_EXP: usize, W: Word> Mul<u128> for Elt<'a, R_EXP, W> {
+    type Output = Elt<'a, R_EXP, W>;
 
-    #[inline] fn mul(self, other: u128) -> Elt<'a, R_EXP> {
+    #[inline] fn mul(self, other: u128) -> Elt<'a, R_EXP, W> {
         self * self.space.enter(other)
     }
 }
 
-impl<'a, const R_EXP: usize> Sub for Elt<'a, R_EXP> {
-    type Output = Elt<'a, R_EXP>;
+impl<'a, const R_EXP: usize, W: Word> Sub for Elt<'a, R_EXP, W> {
+    type Output = Elt<'a, R_EXP, W>;
+
+    #[inline] fn sub(self, other: Elt<'a, R_EXP, W>) -> Elt<'a, R_EXP, W> {
+        Elt {
+            val: self.space.sub(self.val, other.val),
+            space: self.space
+        }
+    }
+}
+
+impl<'a, const R_EXP: usize, W: Word> Div for Elt<'a, R_EXP, W> {
+    type Output = Elt<'a, R_EXP, W>;
+
+    /// Panics if `other` isn't a unit, in the same way integer division panics on zero.
+    #[inline] fn div(self, other: Elt<'a, R_EXP, W>) -> Elt<'a, R_EXP, W> {
+        let inv = other.inverse().expect("attempt to divide by a non-unit");
 
-    #[inline] fn sub(self, other: Elt<'a, R_EXP>) -> Elt<'a, R_EXP> {
         Elt {
-            val: (self.val + (self.space.n - other.val)) % self.space.n,
+            val: self.space.mul(self.val, inv.val),
             space: self.space
         }
     }
 }
+
+impl<'a, const R_EXP: usize, W: Word> Div<u128> for Elt<'a, R_EXP, W> {
+    type Output = Elt<'a, R_EXP, W>;
+
+    #[inline] fn div(self, other: u128) -> Elt<'a, R_EXP, W> {
+        self / self.space.enter(other)
+    }
+}
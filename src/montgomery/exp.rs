@@ -0,0 +1,134 @@
+use crate::montgomery::{Elt, Space, Word};
+
+/// The widest window an `ExpPlan` may use. It bounds the table of odd powers that `exp_with`
+/// keeps on the stack, at `2^(MAX_WINDOW - 1)` entries.
+pub const MAX_WINDOW: u32 = 6;
+
+/// A precomputed sliding-window schedule for raising to a fixed exponent.
+///
+/// The binary ladder in `Elt::exp` does one multiply per set bit of the exponent. A sliding window
+/// of width `k` instead precomputes the odd powers `g, g^3, ..., g^(2^k - 1)` and consumes up to
+/// `k` bits at a time, so a run of set bits costs one multiply per window rather than one per bit.
+/// The squarings are the same either way.
+///
+/// Working out where the windows fall only depends on the exponent, and `Space::legendre` always
+/// raises to the same `(n-1)/2`, so the space builds this once and every legendre test reuses it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpPlan {
+    pub exponent: u128,
+    /// The window width, `k`.
+    pub window: u32,
+    /// The leading window, which seeds the accumulator. Zero only for the zero exponent.
+    pub first: u128,
+    /// After the first window: square `squares` times, then multiply by `g^digit` if `digit` isn't
+    /// zero. `digit` is always odd and below `2^window`.
+    pub steps: Vec<(u32, u128)>,
+}
+
+impl ExpPlan {
+    /// Plans `exponent` with a window width chosen from its length.
+    pub fn new(exponent: u128) -> ExpPlan {
+        let bits = 128 - exponent.leading_zeros();
+        ExpPlan::with_window(exponent, Self::window_for(bits))
+    }
+
+    /// A rough table of the width that minimises `2^(k-1)` precomputed powers plus `bits / (k+1)`
+    /// window multiplies, on average.
+    pub fn window_for(bits: u32) -> u32 {
+        match bits {
+            0..=7 => 1,
+            8..=23 => 2,
+            24..=79 => 3,
+            80..=239 => 4,
+            _ => 5,
+        }
+    }
+
+    pub fn with_window(exponent: u128, window: u32) -> ExpPlan {
+        assert!((1..=MAX_WINDOW).contains(&window), "window must be between 1 and {} bits", MAX_WINDOW);
+
+        let mut plan = ExpPlan { exponent, window, first: 0, steps: vec![] };
+
+        // Scan from the most significant bit down, cutting out windows that start and end on a
+        // set bit.
+        let mut i = 128 - exponent.leading_zeros() as i32 - 1;
+        let mut pending_squares = 0;
+
+        while i >= 0 {
+            if (exponent >> i) & 1 == 0 {
+                pending_squares += 1;
+                i -= 1;
+                continue;
+            }
+
+            // The window is bits i..=j, with j as low as the width allows and landing on a one.
+            let mut j = (i - window as i32 + 1).max(0);
+            while (exponent >> j) & 1 == 0 { j += 1; }
+
+            let len = (i - j + 1) as u32;
+            let digit = (exponent >> j) & ((1 << len) - 1);
+
+            if plan.first == 0 && plan.steps.is_empty() {
+                plan.first = digit;
+            } else {
+                plan.steps.push((pending_squares + len, digit));
+            }
+
+            pending_squares = 0;
+            i = j - 1;
+        }
+
+        if pending_squares > 0 {
+            plan.steps.push((pending_squares, 0));
+        }
+
+        plan
+    }
+}
+
+impl<const R_EXP: usize, W: Word> Space<R_EXP, W> {
+    /// Raises the Montgomery form `base` to `plan.exponent`, with the space's constants passed in
+    /// directly, like `mul_with`. The plan mustn't be for the zero exponent, whose power is the
+    /// space's `1` and isn't known here.
+    #[inline] pub fn exp_with_plan(base: W, plan: &ExpPlan, n: W, n_prime: W) -> W {
+        debug_assert!(plan.first != 0, "the zero exponent has no windows");
+        let mul = |a, b| Self::mul_with(a, b, n, n_prime);
+
+        // table[i] = g^(2i + 1), on the stack rather than allocated on every call.
+        let mut table = [W::ZERO; 1 << (MAX_WINDOW - 1)];
+        let square = mul(base, base);
+        table[0] = base;
+        for i in 1..1 << (plan.window - 1) {
+            table[i] = mul(table[i - 1], square);
+        }
+
+        let mut acc = table[(plan.first >> 1) as usize];
+
+        for &(squares, digit) in &plan.steps {
+            for _ in 0..squares {
+                acc = mul(acc, acc);
+            }
+            if digit != 0 {
+                acc = mul(acc, table[(digit >> 1) as usize]);
+            }
+        }
+
+        acc
+    }
+}
+
+impl<'a, const R_EXP: usize, W: Word> Elt<'a, R_EXP, W> {
+    /// Raises to `plan.exponent` following a precomputed sliding-window schedule.
+    pub fn exp_with(&self, plan: &ExpPlan) -> Elt<'a, R_EXP, W> {
+        if plan.first == 0 { return self.space.enter(1); }
+
+        let val = Space::<R_EXP, W>::exp_with_plan(self.val, plan, self.space.n, self.space.n_prime);
+        Elt { val, space: self.space }
+    }
+
+    /// Sliding-window exponentiation, planning the windows on the fly. Worth it over `exp` when
+    /// the exponent is large; for a fixed exponent, build an `ExpPlan` once and use `exp_with`.
+    pub fn exp_sliding(&self, e: u128) -> Elt<'a, R_EXP, W> {
+        self.exp_with(&ExpPlan::new(e))
+    }
+}
@@ -0,0 +1,181 @@
+use crate::math::square::isqrt;
+use crate::montgomery::{Elt, Space, Word};
+
+/// Below this the forward product is cheaper than the sample doubling. The transforms carry a lot
+/// of overhead, and they only pull ahead at around a million on a 61b prime.
+pub(super) const SUBLINEAR_CUTOFF: u128 = 1 << 21;
+
+impl<const R_EXP: usize, W: Word> Space<R_EXP, W> {
+    /// `n!` in `O(sqrt(n) log(n))` multiplies, for a prime modulus `p`.
+    ///
+    /// Anything past `p/2` is reflected through Wilson's theorem first (see
+    /// `reflected_factorial`), so the work is governed by whichever of `n` and `p - 1 - n` is
+    /// smaller. Small inputs are left to the forward product.
+    pub fn sublinear_factorial(&self, n: u128) -> Elt<'_, R_EXP, W> {
+        let p = self.n.to_u128();
+        if n >= p { return self.enter(0); }
+
+        let reflect = p - 1 - n < n;
+        let m = if reflect { p - 1 - n } else { n };
+
+        let f = if m < SUBLINEAR_CUTOFF {
+            self.factorial(m)
+        } else {
+            self.sampled_factorial(m).unwrap_or_else(|| self.factorial(m))
+        };
+
+        if !reflect { return f; }
+
+        // (p-1-n)! is a product of units, so it's always invertible.
+        let inv = f.inverse().unwrap();
+        if n & 1 == 1 { inv } else { self.enter(0) - inv }
+    }
+
+    /// `n!` by sampling a polynomial, without any of the dispatch in `sublinear_factorial`.
+    ///
+    /// With `v = floor(sqrt(n))`, let `g(x) = (vx + 1)(vx + 2)...(vx + v)`. Then
+    ///
+    /// ```text
+    /// n! = g(0) g(1) ... g(v-1) * (v^2 + 1)...(n)
+    /// ```
+    ///
+    /// so given the `v` values of `g`, there are fewer than `2v` multiplies left. Those values come
+    /// from `factorial_samples`.
+    ///
+    /// Returns `None` if the sampling ran into a zero divisor, which can only happen when the
+    /// modulus isn't prime or `n` is above `p/2`.
+    pub fn sampled_factorial(&self, n: u128) -> Option<Elt<'_, R_EXP, W>> {
+        if n == 0 { return Some(self.enter(1)); }
+
+        let v = isqrt(n);
+        let samples = self.factorial_samples(v)?;
+
+        let mut acc = self.enter(1);
+        for &s in &samples[..v as usize] {
+            acc *= s;
+        }
+        for k in v * v + 1..=n {
+            acc *= k;
+        }
+
+        Some(acc)
+    }
+
+    /// The values of `g_v(x) = (vx + 1)(vx + 2)...(vx + v)` at `x = 0, 1, ..., v`.
+    ///
+    /// This is the Bostan-Gaudry-Schost approach, which never writes `g` down as a polynomial.
+    /// Instead we keep the `d + 1` values of `g_d(x) = (vx + 1)...(vx + d)` at `x = 0, ..., d`,
+    /// which determine it, and build up `d` bit by bit, like an exponentiation ladder:
+    ///
+    /// ```text
+    /// g_2d(x)  = g_d(x) g_d(x + d/v)
+    /// g_d+1(x) = g_d(x) (vx + d + 1)
+    /// ```
+    ///
+    /// The doubling needs `g_d` at `d + 1..=2d + 1`, `d/v..=d/v + d` and `d/v + d + 1..=d/v + 2d
+    /// + 1`, each a run of `d + 1` points past the ones we hold, which `shift_samples` finds with
+    /// one convolution. So each step costs `O(d log d)`, and the total is dominated by the last.
+    pub fn factorial_samples(&self, v: u128) -> Option<Vec<Elt<'_, R_EXP, W>>> {
+        let v_inv = self.enter(v).inverse()?;
+
+        let mut d = 1;
+        let mut samples = vec![self.enter(1), self.enter(v + 1)];
+
+        for bit in (0..127 - v.leading_zeros()).rev() {
+            let d_over_v = v_inv * d;
+
+            let upper = self.shift_samples(&samples, self.enter(d + 1))?;
+            let shifted = self.shift_samples(&samples, d_over_v)?;
+            let shifted_upper = self.shift_samples(&samples, d_over_v + (d + 1))?;
+
+            samples = samples.iter().chain(&upper)
+                .zip(shifted.iter().chain(&shifted_upper))
+                .take(2 * d as usize + 1)
+                .map(|(&a, &b)| a * b)
+                .collect();
+            d *= 2;
+
+            if (v >> bit) & 1 == 1 {
+                for (x, s) in samples.iter_mut().enumerate() {
+                    *s *= v * x as u128 + d + 1;
+                }
+                let top = v * (d + 1);
+                samples.push((1..=d + 1).fold(self.enter(1), |acc, i| acc * (top + i)));
+                d += 1;
+            }
+        }
+
+        Some(samples)
+    }
+
+    /// Given the values `h(0), ..., h(d)` of a polynomial of degree at most `d`, the values
+    /// `h(m), ..., h(m + d)`. Returns `None` if any of `m - d, ..., m + d` is zero, or `d` isn't
+    /// below the modulus, which are the points Lagrange interpolation would have to divide by.
+    ///
+    /// Lagrange interpolation on `0..=d` gives
+    ///
+    /// ```text
+    /// h(m + k) = prod_j (m + k - j) * sum_i h(i) / (i! (d-i)! (-1)^(d-i)) / (m + k - i)
+    /// ```
+    ///
+    /// The sum is the convolution of those weighted samples with `1 / (m - d + t)` for `t` in
+    /// `0..=2d`, read off at `d + k`. The leading product is a sliding window over the same
+    /// `m - d + t`, so it reuses their inverses.
+    pub fn shift_samples<'a>(&'a self, h: &[Elt<'a, R_EXP, W>], m: Elt<'a, R_EXP, W>) -> Option<Vec<Elt<'a, R_EXP, W>>> {
+        let d = h.len() - 1;
+        let one = self.enter(1);
+
+        // factorials[i] = i!, and their inverses by the same trick as `batch_inverse`.
+        let mut factorials = Vec::with_capacity(d + 1);
+        let mut acc = one;
+        factorials.push(acc);
+        for i in 1..=d {
+            acc *= i as u128;
+            factorials.push(acc);
+        }
+        let mut inv_factorials = vec![factorials[d].inverse()?; d + 1];
+        for i in (1..=d).rev() {
+            inv_factorials[i - 1] = inv_factorials[i] * i as u128;
+        }
+
+        let weighted : Vec<Elt<'a, R_EXP, W>> = h.iter().enumerate().map(|(i, &hi)| {
+            let w = hi * inv_factorials[i] * inv_factorials[d - i];
+            if (d - i) & 1 == 1 { self.enter(0) - w } else { w }
+        }).collect();
+
+        // points[t] = m - d + t
+        let mut points = Vec::with_capacity(2 * d + 1);
+        let mut point = m - self.enter(d as u128);
+        for _ in 0..=2 * d {
+            points.push(point);
+            point += one;
+        }
+        let inv_points = self.batch_inverse(&points)?;
+
+        let sums = self.convolve(&weighted, &inv_points);
+
+        // window = (m + k - d)...(m + k), starting from k = 0.
+        let mut window = points[..=d].iter().fold(one, |acc, &x| acc * x);
+        let mut out = Vec::with_capacity(d + 1);
+        for k in 0..=d {
+            out.push(sums[d + k] * window);
+            if k < d {
+                window = window * points[d + k + 1] * inv_points[k];
+            }
+        }
+
+        Some(out)
+    }
+}
+
+/// `n! mod p`, for a prime `p`, by `Space::sublinear_factorial` in the narrowest space that holds
+/// `p`.
+pub fn factorial_mod(n: u128, p: u128) -> u128 {
+    if p == 2 { return if n < 2 { 1 } else { 0 }; }
+
+    if p >> 64 == 0 {
+        Space::<64, u64>::new(p).sublinear_factorial(n).exit()
+    } else {
+        Space::<128>::new(p).sublinear_factorial(n).exit()
+    }
+}
@@ -1,9 +1,21 @@
 pub mod space;
 pub mod elt;
+pub mod word;
+pub mod batch;
+pub mod residue;
+pub mod sqrt;
+pub mod exp;
+pub mod ntt;
+pub mod factorial;
 
 
-pub use space::Space;
+pub use space::{Space, SpaceError};
 pub use elt::Elt;
+pub use word::Word;
+pub use batch::SpaceBatch;
+pub use residue::Residue;
+pub use exp::{ExpPlan, MAX_WINDOW};
+pub use factorial::factorial_mod;
 
 #[cfg(test)]
 pub mod test_case;
@@ -115,109 +127,694 @@ mod tests {
     mod props {
         use super::*;
 
+        // The same properties should hold regardless of the radix or the word the residues live
+        // in, so they're stamped out once per backend. The prime is used for the legendre
+        // property, and needs to fit in the word.
+        macro_rules! montgomery_props {
+            ($name:ident, $r_exp:expr, $word:ty, $prime:expr) => {
+                mod $name {
+                    use super::*;
 
-        mod legendre {
+                    const R_EXP: usize = $r_exp;
+                    type W = $word;
+
+                    mod legendre {
+                        use super::*;
+
+                        #[quickcheck]
+                        fn montgomery_legendre_is_naive_legendre(a: u128) -> bool {
+
+                            let n = $prime;
+
+                            let naive = LegendreSymbol::naive_legendre(a, n);
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let a = space.enter(a);
+                            let montgomery = space.legendre(a);
+
+                            naive == montgomery
+                        }
+
+                        #[quickcheck]
+                        fn montgomery_jacobi_is_naive_legendre(a: u128) -> bool {
+                            let n = $prime;
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+
+                            LegendreSymbol::naive_legendre(a, n) == space.jacobi(space.enter(a))
+                        }
+
+                    }
+
+                    mod factorial {
+                        use super::*;
+
+                        #[quickcheck]
+                        fn montgomery_factorial_is_naive_factorial(k: u8, n: u128) -> bool {
+                            if Space::<{R_EXP}, W>::try_new(n).is_err() { return true; }
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let montgomery = space.factorial(k as u128);
+
+                            let naive = (1..=k.into()).fold(1, |acc, x| mod_mult(acc, x, n));
+
+                            naive == montgomery.exit()
+                        }
+                    }
+
+                    mod montgomery_ops {
+                        use super::*;
+
+                        #[quickcheck]
+                        fn montgomery_add_is_naive_add(tc: TestCase) -> bool {
+                            let TestCase {a, b, n, r_exp: _} = tc;
+
+                            let naive = a.wrapping_add(b) % n;
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let a = space.enter(a);
+                            let b = space.enter(b);
+                            let montgomery = (a + b).exit();
+
+                            naive == montgomery
+                        }
+
+                        #[quickcheck]
+                        fn montgomery_mul_of_u128_is_correct(tc: TestCase) -> bool {
+                            let TestCase {a, b, n, r_exp: _} = tc;
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let x = space.enter(a);
+                            let y = space.enter(b);
+
+                            (x + b) == (x + y)
+                        }
+
+                        #[quickcheck]
+                        fn montgomery_mul_is_naive_mul(tc: TestCase) -> bool {
+                            let TestCase {a, b, n, r_exp: _} = tc;
+
+                            let naive = mod_mult(a, b, n);
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let a = space.enter(a);
+                            let b = space.enter(b);
+                            let montgomery = (a * b).exit();
+
+                            naive == montgomery
+                        }
+
+                        #[quickcheck]
+                        fn bare_space_has_the_same_arithmetic(tc: TestCase) -> bool {
+                            let TestCase {a, b, n, r_exp: _} = tc;
+
+                            let full = Space::<{R_EXP}, W>::new(n);
+                            let bare = Space::<{R_EXP}, W>::new_bare(n);
+
+                            (bare.enter(a) * bare.enter(b)).exit() == (full.enter(a) * full.enter(b)).exit()
+                                && bare.r_inv == full.r_inv
+                                && mod_mult(full.r_inv.to_u128(), full.enter(1).val.to_u128(), n) == 1
+                        }
+
+                        #[quickcheck]
+                        fn montgomery_add_of_u128_is_correct(tc: TestCase) -> bool {
+                            let TestCase {a, b, n, r_exp: _} = tc;
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let x = space.enter(a);
+                            let y = space.enter(b);
+
+                            (x * b) == (x * y)
+                        }
+
+                        #[quickcheck]
+                        fn montgomery_sub_is_naive_sub(tc: TestCase) -> bool {
+                            let TestCase {a, b, n, r_exp: _} = tc;
+
+                            let naive = (a + (n - (b % n))) % n;
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let a = space.enter(a);
+                            let b = space.enter(b);
+                            let montgomery = (a - b).exit();
+
+                            naive == montgomery
+                        }
+
+                        #[quickcheck]
+                        fn montgomery_sliding_exp_is_exp(tc: TestCase, e: u128) -> bool {
+                            let TestCase {a, b: _, n, r_exp: _} = tc;
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let a = space.enter(a);
+
+                            a.exp_sliding(e) == a.exp(e)
+                        }
+
+                        #[quickcheck]
+                        fn montgomery_inverse_is_inverse(tc: TestCase) -> bool {
+                            let TestCase {a, b: _, n, r_exp: _} = tc;
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let a = space.enter(a);
+
+                            match a.inverse() {
+                                Some(inv) => a * inv == space.enter(1),
+                                None => gcd(a.exit(), n) != 1,
+                            }
+                        }
+
+                        #[quickcheck]
+                        fn montgomery_div_undoes_mul(tc: TestCase) -> bool {
+                            let TestCase {a, b, n, r_exp: _} = tc;
+                            if gcd(b, n) != 1 { return true; }
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let a = space.enter(a);
+
+                            (a * b) / b == a
+                        }
+
+                        #[quickcheck]
+                        fn montgomery_batch_inverse_is_inverse(vals: Vec<u128>) -> bool {
+                            let n = $prime;
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let elts : Vec<_> = vals.iter().map(|&v| space.enter(v)).collect();
+                            let singles : Option<Vec<_>> = elts.iter().map(|e| e.inverse()).collect();
+
+                            space.batch_inverse(&elts) == singles
+                        }
+
+                        #[quickcheck]
+                        fn montgomery_exp_is_naive_exp(tc: TestCase, e: u128) -> bool {
+                            let TestCase {a, b: _, n, r_exp: _} = tc;
+
+                            let naive = mod_exp(a, e, n) % n;
+
+                            let space = Space::<{R_EXP}, W>::new(n);
+                            let montgomery = space.enter(a).exp(e).exit();
+
+                            naive == montgomery
+                        }
+                    }
+                }
+            };
+        }
+
+        montgomery_props!(u32_r_32, 32, u32, (1 << 31) - 1);
+        montgomery_props!(u64_r_64, 64, u64, (1 << 61) - 1); //A friendly Mersenne Prime Appears
+        montgomery_props!(r_64, 64, u128, (1 << 61) - 1);
+        montgomery_props!(r_128, 128, u128, (1 << 61) - 1);
+
+        mod sqrt {
             use super::*;
 
+            // Primes chosen for their 2-adic valuation of p - 1: 1 (the Mersenne), 23, and 32
+            // (the "Goldilocks" prime), so both algorithms see short and long Sylow chains.
+            const PRIMES: [u128; 3] = [(1 << 61) - 1, 998_244_353, 0xFFFF_FFFF_0000_0001];
+
+            fn check(root: Option<(Elt<64, u64>, Elt<64, u64>)>, a: Elt<64, u64>) -> bool {
+                match root {
+                    Some((r, s)) => r * r == a && s * s == a && r.exit() <= s.exit(),
+                    None => a.legendre() == LegendreSymbol::Nonresidue,
+                }
+            }
+
             #[quickcheck]
-            fn montgomery_legendre_is_naive_legendre(a: u128) -> bool {
+            fn tonelli_shanks_squares_back(a: u64) -> bool {
+                PRIMES.iter().all(|&p| {
+                    let space = Space::<64, u64>::new(p);
+                    let a = space.enter(a as u128);
+                    check(a.sqrt_tonelli_shanks(), a)
+                })
+            }
 
-                let n = (1 << 61) - 1; //A friendly Mersenne Prime Appears
+            #[quickcheck]
+            fn cipolla_squares_back(a: u64) -> bool {
+                PRIMES.iter().all(|&p| {
+                    let space = Space::<64, u64>::new(p);
+                    let a = space.enter(a as u128);
+                    check(a.sqrt_cipolla(), a)
+                })
+            }
 
-                let naive = LegendreSymbol::naive_legendre(a, n);
+            #[quickcheck]
+            fn methods_agree(a: u64) -> bool {
+                PRIMES.iter().all(|&p| {
+                    let space = Space::<64, u64>::new(p);
+                    let a = space.enter(a as u128);
+                    a.sqrt() == a.sqrt_cipolla() && a.sqrt() == a.sqrt_tonelli_shanks()
+                })
+            }
+
+            #[test]
+            fn roots_of_squares_are_found() {
+                let space = Space::<128>::new((1 << 127) - 1);
+                let k = space.enter(5_040_123_456_789);
 
-                let space = Space::<{R_EXP}>::new(n);
+                let (r, s) = (k * k).sqrt().unwrap();
+
+                assert!(r == k || s == k);
+            }
+
+            #[test]
+            fn zero_is_its_own_root() {
+                let space = Space::<64, u64>::new(998_244_353);
+                let zero = space.enter(0);
+
+                assert_eq!(zero.sqrt(), Some((zero, zero)));
+            }
+        }
+
+        mod exp_plan {
+            use super::*;
+            use crate::montgomery::test_case::WideTestCase;
+
+            #[quickcheck]
+            fn every_window_agrees_with_the_ladder(tc: WideTestCase, window: u8) -> bool {
+                let WideTestCase {a, b: e, n} = tc;
+                let window = 1 + (window as u32 % MAX_WINDOW);
+
+                let space = Space::<128>::new(n);
                 let a = space.enter(a);
-                let montgomery = space.legendre(a);
 
-                naive == montgomery
+                a.exp_with(&ExpPlan::with_window(e, window)) == a.exp(e)
             }
 
+            #[quickcheck]
+            fn plan_reconstructs_exponent(e: u128, window: u8) -> bool {
+                let plan = ExpPlan::with_window(e, 1 + (window as u32 % MAX_WINDOW));
+
+                let rebuilt = plan.steps.iter().fold(plan.first, |acc, &(squares, digit)| {
+                    (acc << squares) + digit
+                });
+
+                rebuilt == e
+            }
+
+            #[test]
+            fn zero_exponent_is_one() {
+                let space = Space::<64, u64>::new(97);
+                assert_eq!(space.enter(5).exp_with(&ExpPlan::new(0)), space.enter(1));
+            }
         }
 
-        mod factorial {
+        mod validation {
             use super::*;
+            use crate::montgomery::test_case::WideTestCase;
 
             #[quickcheck]
-            fn montgomery_factorial_is_naive_factorial(k: u8, n: u128) -> bool {
-                if gcd(n, R_EXP as u128) != 1 { return true; }
+            fn valid_moduli_are_accepted(tc: TestCase) -> bool {
+                Space::<32, u32>::try_new(tc.n).is_ok()
+                    && Space::<64, u64>::try_new(tc.n).is_ok()
+                    && Space::<128>::try_new(tc.n).is_ok()
+            }
 
-                let space = Space::<{R_EXP}>::new(n);
-                let montgomery = space.factorial(k as u128);
+            #[quickcheck]
+            fn even_moduli_are_rejected(tc: TestCase) -> bool {
+                let n = tc.n << 1;
+                Space::<128>::try_new(n) == Err(SpaceError::EvenModulus(n))
+            }
 
-                let naive = (1..=k.into()).fold(1, |acc, x| mod_mult(acc, x, n));
+            #[quickcheck]
+            fn wide_moduli_are_rejected_by_narrow_spaces(tc: WideTestCase) -> bool {
+                Space::<64, u64>::try_new(tc.n) == Err(SpaceError::ModulusTooLarge { n: tc.n, r_exp: 64 })
+                    && Space::<128>::try_new(tc.n).is_ok()
+            }
 
-                naive == montgomery.exit()
+            #[test]
+            fn degenerate_moduli_are_rejected() {
+                assert_eq!(Space::<64, u64>::try_new(0), Err(SpaceError::ZeroModulus));
+                assert_eq!(Space::<64, u64>::try_new(1), Err(SpaceError::UnitModulus));
+            }
+
+            #[test]
+            fn radix_wider_than_word_is_rejected() {
+                assert_eq!(
+                    Space::<64, u32>::try_new(7),
+                    Err(SpaceError::InvalidRadix { r_exp: 64, word_bits: 32 })
+                );
+            }
+
+            #[test]
+            #[should_panic(expected = "invalid montgomery space")]
+            fn new_panics_on_invalid_moduli() {
+                Space::<64, u64>::new(10);
+            }
+
+            #[test]
+            #[should_panic(expected = "legendre needs a space built by `try_new`")]
+            fn legendre_panics_on_a_bare_space() {
+                let space = Space::<64, u64>::new_bare(1_000_003);
+                space.legendre(space.enter(2));
             }
         }
 
-        mod montgomery_ops {
+        mod wilson {
             use super::*;
+            use crate::math::prime::primes_from;
+
+            #[quickcheck]
+            fn prime_factorial_is_factorial(n: u16, which: u8) -> bool {
+                let p = primes_from(3).nth(which as usize).unwrap();
+                let space = Space::<64, u64>::new(p);
+                let n = n as u128 % (2 * p);
 
-            const R_EXP: usize = 64;
+                space.prime_factorial(n) == space.factorial(n)
+            }
 
             #[quickcheck]
-            fn montgomery_add_is_naive_add(tc: TestCase) -> bool {
-                let TestCase {a, b, n, r_exp: _} = tc;
+            fn reflected_factorial_is_factorial(n: u16) -> bool {
+                let p = 65_537;
+                let space = Space::<64, u64>::new(p);
+                let n = n as u128;
+
+                match space.reflected_factorial(n) {
+                    Some(f) => f == space.factorial(n),
+                    None => n >= p,
+                }
+            }
 
-                let naive = a.wrapping_add(b) % n;
+            #[test]
+            fn reflection_near_a_large_prime() {
+                // 2^61 - 1 is far beyond anything the forward product could reach.
+                let p = (1 << 61) - 1;
+                let space = Space::<64, u64>::new(p);
+
+                // (p-1)! = -1, and (p-2)! = 1, by Wilson
+                assert_eq!(space.prime_factorial(p - 1).exit(), p - 1);
+                assert_eq!(space.prime_factorial(p - 2).exit(), 1);
+            }
 
-                let space = Space::<{R_EXP}>::new(n);
-                let a = space.enter(a);
-                let b = space.enter(b);
-                let montgomery = (a + b).exit();
+            #[quickcheck]
+            fn batch_prime_factorial_is_factorial(n: u16) -> bool {
+                let primes : Vec<u128> = primes_from(60_000).take(8).collect();
+                let mut batch = SpaceBatch::<64, u64>::new(&primes);
+                batch.set_prime_factorial(n as u128);
+                let prime = batch.residues.clone();
+                batch.set_factorial(n as u128);
+
+                prime == batch.residues
+            }
+        }
 
-                naive == montgomery
+        mod sublinear {
+            use super::*;
+
+            // A small prime, a Mersenne prime, and the largest prime below 2^64, none of which has
+            // a useful power of two in p - 1.
+            const PRIMES: [u128; 3] = [65_537, (1 << 61) - 1, 0xFFFF_FFFF_FFFF_FFC5];
+
+            fn random_elts<'a>(space: &'a Space<64, u64>, vals: &[u64]) -> Vec<Elt<'a, 64, u64>> {
+                vals.iter().map(|&v| space.enter(v as u128)).collect()
+            }
+
+            #[quickcheck]
+            fn ntt_convolution_is_schoolbook(a: Vec<u64>, b: Vec<u64>) -> bool {
+                PRIMES.iter().all(|&p| {
+                    let space = Space::<64, u64>::new(p);
+                    let (a, b) = (random_elts(&space, &a), random_elts(&space, &b));
+
+                    space.convolve_ntt(&a, &b) == space.convolve_schoolbook(&a, &b)
+                })
+            }
+
+            #[quickcheck]
+            fn wide_ntt_convolution_is_schoolbook(a: Vec<u128>, b: Vec<u128>) -> bool {
+                let space = Space::<128>::new((1 << 127) - 1);
+                let a : Vec<_> = a.iter().map(|&v| space.enter(v)).collect();
+                let b : Vec<_> = b.iter().map(|&v| space.enter(v)).collect();
+
+                space.convolve_ntt(&a, &b) == space.convolve_schoolbook(&a, &b)
+            }
+
+            #[test]
+            fn long_convolution_is_schoolbook() {
+                let space = Space::<64, u64>::new((1 << 61) - 1);
+                let a : Vec<_> = (0..300u128).map(|i| space.enter(i * i * 0x9E37_79B9_7F4A_7C15)).collect();
+                let b : Vec<_> = (0..200u128).map(|i| space.enter(i ^ 0xDEAD_BEEF_CAFE)).collect();
+
+                assert_eq!(space.convolve(&a, &b), space.convolve_schoolbook(&a, &b));
+            }
+
+            #[test]
+            fn sampled_factorial_is_factorial() {
+                for &p in PRIMES.iter() {
+                    let space = Space::<64, u64>::new(p);
+                    let mut forward = space.enter(1);
+
+                    for n in 1..=3_000u128 {
+                        forward *= n;
+                        if n % 37 == 0 || n.is_power_of_two() || (n + 1).is_power_of_two() {
+                            assert_eq!(space.sampled_factorial(n), Some(forward), "{}! mod {}", n, p);
+                        }
+                    }
+                }
+            }
+
+            #[test]
+            fn wide_sampled_factorial_is_factorial() {
+                let space = Space::<128>::new((1 << 127) - 1);
+                assert_eq!(space.sampled_factorial(1_000), Some(space.factorial(1_000)));
+            }
+
+            #[test]
+            fn sublinear_factorial_is_factorial() {
+                // Large enough that both sides of the reflection get past the cutoff.
+                let p = 10_000_019;
+                let space = Space::<64, u64>::new(p);
+
+                for n in [0, 1, 3_000_000, p / 2, p - 3_000_000, p - 1] {
+                    assert_eq!(space.sublinear_factorial(n), space.factorial(n), "{}!", n);
+                }
+            }
+
+            #[test]
+            fn half_factorial_squares_to_plus_or_minus_one() {
+                // ((p-1)/2)!^2 = (-1)^((p+1)/2) mod p, a consequence of Wilson's theorem.
+                for p in [998_244_353u128, 1_000_000_007] {
+                    let f = factorial_mod((p - 1) / 2, p);
+                    let expected = if p % 4 == 1 { p - 1 } else { 1 };
+                    assert_eq!(mod_mult(f, f, p), expected);
+                }
+            }
+        }
+
+        mod batch {
+            use super::*;
+            use crate::math::prime::primes_from;
+
+            fn primes() -> Vec<u128> {
+                primes_from(1_000_000).take(16).collect()
             }
 
             #[quickcheck]
-            fn montgomery_mul_of_u128_is_correct(tc: TestCase) -> bool {
-                let TestCase {a, b, n, r_exp: _} = tc;
+            fn batch_factorial_is_space_factorial(k: u8) -> bool {
+                let primes = primes();
+                let mut batch = SpaceBatch::<64, u64>::new(&primes);
+                batch.set_factorial(k as u128);
+
+                primes.iter().enumerate().all(|(i, &p)| {
+                    batch.exit(i) == Space::<64, u64>::new(p).factorial(k as u128).exit()
+                })
+            }
 
-                let space = Space::<{R_EXP}>::new(n);
-                let x = space.enter(a);
-                let y = space.enter(b);
+            #[quickcheck]
+            fn batch_legendre_is_space_legendre(k: u8, offset: u128) -> bool {
+                let primes = primes();
+                let mut batch = SpaceBatch::<64, u64>::new(&primes);
+                batch.set_factorial(k as u128);
+                batch.mul_scalar(offset);
+                batch.add_one();
+
+                let mut symbols = vec![LegendreSymbol::Divisor; batch.len()];
+                batch.legendre_all(&mut symbols);
+
+                primes.iter().zip(symbols).all(|(&p, sym)| {
+                    let space = Space::<64, u64>::new(p);
+                    let v = space.factorial(k as u128) * offset + 1;
+                    sym == space.legendre(v)
+                })
+            }
 
-                (x + b) == (x + y)
+            #[quickcheck]
+            fn advance_is_mul_scalar(k: u8, start: u128) -> bool {
+                let start = start >> 1;
+                let mut stepped = SpaceBatch::<64, u64>::new(&primes());
+                stepped.set_factorial(k as u128);
+                let mut scaled = stepped.clone();
+
+                stepped.set_counter(start);
+                (1..=20).all(|j| {
+                    stepped.advance();
+                    scaled.mul_scalar(start + j);
+                    stepped.residues == scaled.residues
+                })
             }
 
             #[quickcheck]
-            fn montgomery_mul_is_naive_mul(tc: TestCase) -> bool {
-                let TestCase {a, b, n, r_exp: _} = tc;
+            fn batch_add_then_sub_is_identity(k: u8) -> bool {
+                let mut batch = SpaceBatch::<128>::new(&primes());
+                batch.set_factorial(k as u128);
+                let before = batch.residues.clone();
 
-                let naive = mod_mult(a, b, n);
+                batch.add_one();
+                batch.sub_one();
 
-                let space = Space::<{R_EXP}>::new(n);
-                let a = space.enter(a);
-                let b = space.enter(b);
-                let montgomery = (a * b).exit();
+                batch.residues == before
+            }
+        }
+
+        mod residue {
+            use super::*;
+            use crate::montgomery::residue::{read_from_file, write_to_file};
+            use crate::montgomery::test_case::{through_file, WideTestCase};
+
+            #[quickcheck]
+            fn residue_round_trips_through_elt(tc: WideTestCase) -> bool {
+                let WideTestCase {a, b: _, n} = tc;
+
+                let space = Space::<128>::new(n);
+                let elt = space.enter(a);
+                let residue = elt.to_residue();
+
+                residue == Residue::new(a, n) && residue.attach(&space) == Some(elt)
+            }
+
+            #[quickcheck]
+            fn residue_moves_between_words(tc: TestCase) -> bool {
+                let TestCase {a, b: _, n, r_exp: _} = tc;
+
+                let narrow = Space::<32, u32>::new(n);
+                let wide = Space::<128>::new(n);
+
+                narrow.enter(a).to_residue().attach(&wide) == Some(wide.enter(a))
+            }
+
+            #[test]
+            fn residue_refuses_other_modulus() {
+                let space = Space::<64, u64>::new(11);
+                assert_eq!(Residue::new(3, 7).attach(&space), None);
+            }
+
+            #[test]
+            fn bad_moduli_are_errors() {
+                assert_eq!(Residue::try_new(3, 0), Err(SpaceError::ZeroModulus));
+                for s in ["1 mod 0", "0 mod 1", "3 mod 8", "7 mod 7"] {
+                    assert!(s.parse::<Residue>().is_err(), "{}", s);
+                }
+
+                let residues = [Residue::new(2, 7), Residue::new(3, 10)];
+                assert_eq!(SpaceBatch::<64, u64>::try_from_residues(&residues).err(), Some(SpaceError::EvenModulus(10)));
+                let wide = [Residue::new(2, (1 << 64) + 13)];
+                assert_eq!(SpaceBatch::<64, u64>::try_from_residues(&wide).err(), Some(SpaceError::ModulusTooLarge { n: (1 << 64) + 13, r_exp: 64 }));
+            }
+
+            #[quickcheck]
+            fn residue_round_trips_through_str(tc: WideTestCase) -> bool {
+                let r = Residue::new(tc.a, tc.n);
+                r.to_string().parse::<Residue>().ok() == Some(r)
+            }
+
+            #[test]
+            fn residues_round_trip_through_file() {
+                let primes : Vec<u128> = crate::math::prime::primes_from(1_000).take(8).collect();
+                let mut batch = SpaceBatch::<64, u64>::new(&primes);
+                batch.set_factorial(100);
+
+                let restored = through_file(
+                    "residues",
+                    |path| write_to_file(path, &batch.to_residues()),
+                    read_from_file,
+                );
+
+                assert_eq!(SpaceBatch::<64, u64>::from_residues(&restored).residues, batch.residues);
+            }
+        }
+
+        /// The same props over the whole `u128` range; see `WideTestCase`.
+        mod wide {
+            use super::*;
+            use crate::montgomery::test_case::WideTestCase;
+
+            #[quickcheck]
+            fn wide_mul_is_naive_mul(tc: WideTestCase) -> bool {
+                let WideTestCase {a, b, n} = tc;
+
+                let space = Space::<128>::new(n);
+                let montgomery = (space.enter(a) * space.enter(b)).exit();
+
+                mod_mult(a, b, n) == montgomery
+            }
+
+            #[quickcheck]
+            fn wide_add_is_naive_add(tc: WideTestCase) -> bool {
+                let WideTestCase {a, b, n} = tc;
+
+                let (sum, carry) = a.overflowing_add(b);
+                let naive = if carry || sum >= n { sum.wrapping_sub(n) } else { sum };
+
+                let space = Space::<128>::new(n);
+                let montgomery = (space.enter(a) + space.enter(b)).exit();
 
                 naive == montgomery
             }
 
             #[quickcheck]
-            fn montgomery_add_of_u128_is_correct(tc: TestCase) -> bool {
-                let TestCase {a, b, n, r_exp: _} = tc;
+            fn wide_sub_is_naive_sub(tc: WideTestCase) -> bool {
+                let WideTestCase {a, b, n} = tc;
+
+                let naive = if a >= b { a - b } else { n - (b - a) };
 
-                let space = Space::<{R_EXP}>::new(n);
-                let x = space.enter(a);
-                let y = space.enter(b);
+                let space = Space::<128>::new(n);
+                let montgomery = (space.enter(a) - space.enter(b)).exit();
 
-                (x * b) == (x * y)
+                naive == montgomery
             }
 
             #[quickcheck]
-            fn montgomery_sub_is_naive_sub(tc: TestCase) -> bool {
-                let TestCase {a, b, n, r_exp: _} = tc;
+            fn wide_factorial_is_naive_factorial(k: u8, tc: WideTestCase) -> bool {
+                let WideTestCase {a: _, b: _, n} = tc;
 
-                let naive = (a + (n - (b % n))) % n;
+                let space = Space::<128>::new(n);
+                let naive = (1..=k.into()).fold(1, |acc, x| mod_mult(acc, x, n));
 
-                let space = Space::<{R_EXP}>::new(n);
+                naive == space.factorial(k as u128).exit()
+            }
+
+            #[quickcheck]
+            fn full_width_u64_mul_is_naive_mul(tc: WideTestCase) -> bool {
+                // The top half of a wide modulus is a 64b modulus with its top bit set.
+                let n = (tc.n >> 64) | 1;
+                let (a, b) = (tc.a % n, tc.b % n);
+
+                let space = Space::<64, u64>::new(n);
+                let montgomery = (space.enter(a) * space.enter(b)).exit();
+
+                mod_mult(a, b, n) == montgomery
+            }
+
+            #[quickcheck]
+            fn wide_inverse_is_inverse(tc: WideTestCase) -> bool {
+                let WideTestCase {a, b: _, n} = tc;
+
+                let space = Space::<128>::new(n);
                 let a = space.enter(a);
-                let b = space.enter(b);
-                let montgomery = (a - b).exit();
 
-                naive == montgomery
+                match a.inverse() {
+                    Some(inv) => (a * inv).exit() == 1,
+                    None => gcd(a.exit(), n) != 1,
+                }
+            }
+
+            #[quickcheck]
+            fn wide_legendre_is_naive_legendre(a: u128) -> bool {
+                let n = (1 << 127) - 1; // A much less friendly Mersenne Prime
+
+                let space = Space::<128>::new(n);
+
+                LegendreSymbol::naive_legendre(a, n) == space.legendre(space.enter(a))
             }
         }
     }
@@ -0,0 +1,194 @@
+use crate::montgomery::{Elt, Space, Word};
+
+/// Primes of the form `c * 2^k + 1` below 2^63, each with a generator of its multiplicative
+/// group. `k` is at least 53 for all of them, so they all have roots of unity of any order a
+/// transform could plausibly need.
+const NTT_PRIMES: [(u128, u128); 5] = [
+    (4_179_340_454_199_820_289, 3), //  29 * 2^57 + 1
+    (6_269_010_681_299_730_433, 5), //  87 * 2^56 + 1
+    (7_097_673_012_735_901_697, 3), // 197 * 2^55 + 1
+    (9_097_271_247_288_401_921, 6), // 505 * 2^54 + 1
+    (8_691_947_280_825_057_281, 3), // 965 * 2^53 + 1
+];
+
+/// A lower bound on the size of every prime in `NTT_PRIMES`, in bits.
+const NTT_PRIME_BITS: u32 = 61;
+
+/// Below this length, on either side, the schoolbook product is cheaper than the transforms.
+const SCHOOLBOOK_CUTOFF: usize = 32;
+
+/// One of the `NTT_PRIMES`, with its space built.
+struct NttPrime {
+    space: Space<64, u64>,
+    generator: u128,
+}
+
+impl NttPrime {
+    fn new((q, generator): (u128, u128)) -> NttPrime {
+        NttPrime { space: Space::new(q), generator }
+    }
+
+    /// An in-place iterative radix-2 transform of `a`, whose length must be a power of two. The
+    /// values are in the prime's Montgomery form on the way in and out. The inverse transform
+    /// includes the division by the length.
+    fn transform(&self, a: &mut [u64], inverse: bool) {
+        let space = &self.space;
+        let q = space.n.to_u128();
+        let n = a.len();
+
+        let mut j = 0;
+        for i in 1..n {
+            let mut bit = n >> 1;
+            while j & bit != 0 {
+                j ^= bit;
+                bit >>= 1;
+            }
+            j |= bit;
+            if i < j { a.swap(i, j); }
+        }
+
+        let mut len = 2;
+        while len <= n {
+            let mut w = space.enter(self.generator).exp((q - 1) / len as u128);
+            if inverse { w = w.inverse().unwrap(); }
+
+            let half = len >> 1;
+            let mut twiddles = Vec::with_capacity(half);
+            let mut cur = space.enter(1);
+            for _ in 0..half {
+                twiddles.push(cur.val);
+                cur *= w;
+            }
+
+            for chunk in a.chunks_exact_mut(len) {
+                let (lo, hi) = chunk.split_at_mut(half);
+                for ((x, y), &w) in lo.iter_mut().zip(hi.iter_mut()).zip(&twiddles) {
+                    let t = space.mul(*y, w);
+                    *y = space.sub(*x, t);
+                    *x = space.add(*x, t);
+                }
+            }
+
+            len <<= 1;
+        }
+
+        if inverse {
+            let n_inv = space.enter(n as u128).inverse().unwrap().val;
+            for x in a.iter_mut() {
+                *x = space.mul(*x, n_inv);
+            }
+        }
+    }
+}
+
+impl<const R_EXP: usize, W: Word> Space<R_EXP, W> {
+    /// The coefficients of the product of the polynomials with coefficients `a` and `b`, lowest
+    /// degree first. Picks between `convolve_schoolbook` and `convolve_ntt` on the lengths.
+    pub fn convolve<'a>(&'a self, a: &[Elt<'a, R_EXP, W>], b: &[Elt<'a, R_EXP, W>]) -> Vec<Elt<'a, R_EXP, W>> {
+        if a.len().min(b.len()) <= SCHOOLBOOK_CUTOFF {
+            self.convolve_schoolbook(a, b)
+        } else {
+            self.convolve_ntt(a, b)
+        }
+    }
+
+    /// The product of two polynomials by the `O(len(a) len(b))` schoolbook method.
+    pub fn convolve_schoolbook<'a>(&'a self, a: &[Elt<'a, R_EXP, W>], b: &[Elt<'a, R_EXP, W>]) -> Vec<Elt<'a, R_EXP, W>> {
+        if a.is_empty() || b.is_empty() { return vec![]; }
+
+        let mut out = vec![self.enter(0); a.len() + b.len() - 1];
+        for (i, &x) in a.iter().enumerate() {
+            for (j, &y) in b.iter().enumerate() {
+                out[i + j] += x * y;
+            }
+        }
+        out
+    }
+
+    /// The product of two polynomials by number theoretic transforms, for any modulus.
+    ///
+    /// The modulus here almost never has the large power of two in `n - 1` that a transform of
+    /// useful length needs, so we don't transform over it at all. Instead the coefficients are
+    /// treated as plain integers below `n`, and their product is computed exactly: each
+    /// coefficient of it is below `len * n^2`, so it's pinned down by its residues mod enough of
+    /// the `NTT_PRIMES`, which we convolve over separately and recombine with Garner's algorithm.
+    ///
+    /// The integers we convolve are the Montgomery forms `aR` and `bR`, so the exact product is
+    /// `abR^2`. Folding the recombination into `mul`, which divides by `R`, lands on `abR`, which
+    /// is the product back in Montgomery form.
+    pub fn convolve_ntt<'a>(&'a self, a: &[Elt<'a, R_EXP, W>], b: &[Elt<'a, R_EXP, W>]) -> Vec<Elt<'a, R_EXP, W>> {
+        if a.is_empty() || b.is_empty() { return vec![]; }
+
+        let n = self.n.to_u128();
+        let out_len = a.len() + b.len() - 1;
+        let size = out_len.next_power_of_two();
+
+        let shortest = a.len().min(b.len());
+        let bound_bits = 2 * (128 - n.leading_zeros()) + (usize::BITS - shortest.leading_zeros());
+        let count = bound_bits.div_ceil(NTT_PRIME_BITS) as usize;
+        assert!(count <= NTT_PRIMES.len(), "convolution is too long to recombine exactly");
+
+        let primes : Vec<NttPrime> = NTT_PRIMES[..count].iter().map(|&q| NttPrime::new(q)).collect();
+
+        // residues[i][k] is coefficient k of the exact product, mod the i'th prime.
+        let residues : Vec<Vec<u64>> = primes.iter().map(|q| {
+            let lift = |elts: &[Elt<'a, R_EXP, W>]| {
+                let mut out = vec![0; size];
+                for (x, e) in out.iter_mut().zip(elts) {
+                    *x = q.space.enter(e.val.to_u128()).val;
+                }
+                q.transform(&mut out, false);
+                out
+            };
+
+            let mut fa = lift(a);
+            let fb = lift(b);
+            for (x, &y) in fa.iter_mut().zip(&fb) {
+                *x = q.space.mul(*x, y);
+            }
+            q.transform(&mut fa, true);
+            fa.truncate(out_len);
+            fa
+        }).collect();
+
+        // Garner writes the exact coefficient as `t_0 + t_1 m_0 + t_2 m_0 m_1 + ...` with each
+        // digit `t_i < m_i`. For each prime we need the partial products of the primes before it,
+        // mod itself, and their inverse; and mod `n` for the final sum.
+        let prefixes : Vec<Vec<Elt<64, u64>>> = primes.iter().map(|qi| {
+            let mut acc = qi.space.enter(1);
+            primes.iter().map(|qj| {
+                let prefix = acc;
+                acc *= qj.space.n as u128;
+                prefix
+            }).collect()
+        }).collect();
+        let inverses : Vec<Elt<64, u64>> = (0..count).map(|i| prefixes[i][i].inverse().unwrap()).collect();
+
+        let mut prefix_n = Vec::with_capacity(count);
+        let mut acc = 1 % n;
+        for q in &primes {
+            prefix_n.push(W::from_u128(acc));
+            acc = crate::math::mod_mult(acc, q.space.n as u128, n);
+        }
+
+        (0..out_len).map(|k| {
+            let mut digits = [0u128; NTT_PRIMES.len()];
+
+            for (i, q) in primes.iter().enumerate() {
+                let mut partial = q.space.enter(0);
+                for j in 0..i {
+                    partial += prefixes[i][j] * digits[j];
+                }
+                let r = Elt { val: residues[i][k], space: &q.space };
+                digits[i] = ((r - partial) * inverses[i]).exit();
+            }
+
+            // `mul` divides by `R`, which is what takes `abR^2` to `abR`.
+            let val = digits[..count].iter().zip(&prefix_n).fold(W::ZERO, |val, (&t, &m)| {
+                self.add(val, self.mul(W::from_u128(t % n), m))
+            });
+
+            Elt { val, space: self }
+        }).collect()
+    }
+}
@@ -0,0 +1,140 @@
+use crate::montgomery::{Elt, Space, SpaceBatch, SpaceError, Word};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::str::FromStr;
+
+/// An owned residue `val mod n`, detached from any `Space`.
+///
+/// An `Elt` borrows its `Space`, which is what makes it cheap to work with, but also means it
+/// can't outlive the space, be sent to another thread, or be written to disk. A `Residue` carries
+/// the modulus with it instead, and holds the value out of Montgomery form, so it doesn't care
+/// which radix or word it came from. It can be attached to any `Space` with the same modulus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Residue {
+    pub val: u128,
+    pub n: u128
+}
+
+impl Residue {
+    /// Panics if `n` is zero; see `try_new`.
+    pub fn new(val: u128, n: u128) -> Residue {
+        Self::try_new(val, n).unwrap_or_else(|e| panic!("invalid residue: {}", e))
+    }
+
+    /// `val mod n`, or `SpaceError::ZeroModulus` if there's nothing to reduce by.
+    pub fn try_new(val: u128, n: u128) -> Result<Residue, SpaceError> {
+        if n == 0 { return Err(SpaceError::ZeroModulus); }
+        Ok(Residue { val: val % n, n })
+    }
+
+    /// Brings the residue back into `space`, or `None` if the space has a different modulus.
+    pub fn attach<'a, const R_EXP: usize, W: Word>(&self, space: &'a Space<R_EXP, W>) -> Option<Elt<'a, R_EXP, W>> {
+        if space.n.to_u128() != self.n { return None; }
+
+        Some(space.enter(self.val))
+    }
+}
+
+impl<const R_EXP: usize, W: Word> From<Elt<'_, R_EXP, W>> for Residue {
+    fn from(elt: Elt<'_, R_EXP, W>) -> Residue {
+        Residue { val: elt.exit(), n: elt.space.n.to_u128() }
+    }
+}
+
+impl<const R_EXP: usize, W: Word> Elt<'_, R_EXP, W> {
+    #[inline] pub fn to_residue(&self) -> Residue {
+        Residue::from(*self)
+    }
+}
+
+impl<const R_EXP: usize, W: Word> SpaceBatch<R_EXP, W> {
+    /// Every lane as an owned residue, in lane order.
+    pub fn to_residues(&self) -> Vec<Residue> {
+        (0..self.len()).map(|i| Residue { val: self.exit(i), n: self.moduli[i].to_u128() }).collect()
+    }
+
+    /// Builds a batch with one lane per residue, holding that residue.
+    ///
+    /// Panics if any modulus can't make a `Space<R_EXP, W>`; see `try_from_residues`.
+    pub fn from_residues(residues: &[Residue]) -> SpaceBatch<R_EXP, W> {
+        Self::try_from_residues(residues).unwrap_or_else(|e| panic!("invalid montgomery space: {}", e))
+    }
+
+    /// `from_residues`, or the first modulus that can't make a `Space<R_EXP, W>`, as
+    /// `SpaceBatch::try_new` reports it. Residues read back from a file go through here.
+    pub fn try_from_residues(residues: &[Residue]) -> Result<SpaceBatch<R_EXP, W>, SpaceError> {
+        let moduli : Vec<u128> = residues.iter().map(|r| r.n).collect();
+        let mut batch = SpaceBatch::try_new(&moduli)?;
+        batch.set_residues(residues);
+        Ok(batch)
+    }
+
+    /// Loads each lane from the residue in the same position. Panics unless there's one residue
+    /// per lane, with that lane's modulus.
+    pub fn set_residues(&mut self, residues: &[Residue]) {
+        assert_eq!(residues.len(), self.len(), "need one residue per lane");
+
+        for (i, r) in residues.iter().enumerate() {
+            let n = self.moduli[i];
+            assert_eq!(r.n, n.to_u128(), "residue is for a different modulus");
+
+            let val = W::from_u128(r.val);
+            self.residues[i] = Space::<R_EXP, W>::mul_with(val, self.r_squareds[i], n, self.n_primes[i]);
+        }
+    }
+}
+
+/// Written as `val mod n`, which is also what `FromStr` expects.
+impl fmt::Display for Residue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} mod {}", self.val, self.n)
+    }
+}
+
+impl FromStr for Residue {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Residue, io::Error> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("not a residue: {:?}", s));
+
+        let (val, n) = s.trim().split_once(" mod ").ok_or_else(invalid)?;
+        let val : u128 = val.parse().map_err(|_| invalid())?;
+        let n : u128 = n.parse().map_err(|_| invalid())?;
+
+        // Only moduli that some `Space` will take are worth writing down; the widest space has
+        // the fewest restrictions.
+        Space::<128>::check_modulus(n)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("not a residue: {:?}: {}", s, e)))?;
+        if val >= n { return Err(invalid()); }
+
+        Ok(Residue { val, n })
+    }
+}
+
+/// Writes one residue per line to `out`.
+pub fn write_residues(out: &mut impl Write, residues: &[Residue]) -> Result<(), io::Error> {
+    for r in residues {
+        writeln!(out, "{}", r)?;
+    }
+    Ok(())
+}
+
+/// Reads residues written by `write_residues`, one per line, skipping blank lines.
+pub fn read_residues(input: impl BufRead) -> Result<Vec<Residue>, io::Error> {
+    input.lines()
+        .filter(|line| !matches!(line, Ok(l) if l.trim().is_empty()))
+        .map(|line| line?.parse())
+        .collect()
+}
+
+/// Writes one residue per line to `filename`, overwriting it.
+pub fn write_to_file(filename: &str, residues: &[Residue]) -> Result<(), io::Error> {
+    let mut file = std::fs::File::create(filename)?;
+    write_residues(&mut file, residues)
+}
+
+/// Reads back a file written by `write_to_file`.
+pub fn read_from_file(filename: &str) -> Result<Vec<Residue>, io::Error> {
+    let file = std::fs::File::open(filename)?;
+    read_residues(BufReader::new(file))
+}
@@ -1,8 +1,46 @@
 use crate::math::{
-    mod_inverse, mod_mult,
-    legendre::LegendreSymbol
+    mod_mult,
+    legendre::{jacobi, LegendreSymbol}
 };
-use crate::montgomery::Elt;
+use crate::montgomery::{factorial::SUBLINEAR_CUTOFF, Elt, ExpPlan, Word};
+use std::fmt;
+
+/// Roughly what an inversion costs, in multiplies, for deciding when Wilson reflection pays off.
+const REFLECTION_OVERHEAD: u128 = 256;
+
+/// The ways a modulus can fail to make a usable Montgomery space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpaceError {
+    /// `R_EXP` must be at least 1 and no wider than the word.
+    InvalidRadix { r_exp: usize, word_bits: usize },
+    /// Zero isn't a modulus.
+    ZeroModulus,
+    /// Everything is zero mod 1, which makes for a very boring space, and breaks `legendre`.
+    UnitModulus,
+    /// `R` is a power of two, so the modulus must be odd for `R` to be invertible mod `n`.
+    EvenModulus(u128),
+    /// REDC needs `n < R`.
+    ModulusTooLarge { n: u128, r_exp: usize },
+}
+
+impl fmt::Display for SpaceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpaceError::InvalidRadix { r_exp, word_bits } =>
+                write!(f, "R_EXP = {} is not in [1, {}] for a {}b word", r_exp, word_bits, word_bits),
+            SpaceError::ZeroModulus =>
+                write!(f, "the modulus must be nonzero"),
+            SpaceError::UnitModulus =>
+                write!(f, "the modulus must be greater than 1"),
+            SpaceError::EvenModulus(n) =>
+                write!(f, "the modulus {} is even, so it shares a factor with R", n),
+            SpaceError::ModulusTooLarge { n, r_exp } =>
+                write!(f, "the modulus {} is not below R = 2^{}", n, r_exp),
+        }
+    }
+}
+
+impl std::error::Error for SpaceError {}
 
 /// A Montgomery Space is a modulus `n` and a Montgomery constant `r` such that `r * r_inv - n *
 /// n_inv = 1`. The Montgomery constant `r` is chosen such that `r > n` and `r` is a power of 2.
@@ -12,31 +50,49 @@ use crate::montgomery::Elt;
 /// together, then multiplying the result by `r_inv` modulo `n` to get `ab mod n`. This can be done
 /// efficiently by the `redc` function. When `r` is chosen to be a power of 2, the `redc` function
 /// can be implemented as a simple bit shift.
+///
+/// Residues are stored in the word `W`, and `R_EXP` may be anything up to and including the width
+/// of that word. When `R_EXP` is the full width the radix itself no longer fits in a `W`, so
+/// products are carried as a double-width `(lo, hi)` pair through `redc_wide`, which lets the
+/// modulus use the entire word. `Space<64, u64>` is the natural choice for primes below 2^64,
+/// `Space<128>` for anything larger.
 #[derive(Debug, Clone)]
-pub struct Space<const R_EXP: usize> {
-    pub n: u128,
-    pub r_inv: u128, 
-    pub r_squared: u128, 
-    pub n_inv: u128,
-    pub n_prime: u128,
-    pub r_n_legendre: LegendreSymbol
+pub struct Space<const R_EXP: usize, W: Word = u128> {
+    pub n: W,
+    pub r_inv: W,
+    pub r_squared: W,
+    pub n_inv: W,
+    pub n_prime: W,
+    /// `(R/n)`. Only meaningful alongside `legendre_plan`.
+    pub r_n_legendre: LegendreSymbol,
+    /// The sliding-window schedule for `(n-1)/2`, the exponent every legendre test uses. `None`
+    /// in a bare space.
+    pub legendre_plan: Option<ExpPlan>
 }
 
 
-impl<const R_EXP: usize> PartialEq for Space<R_EXP> {
-    fn eq(&self, other: &Space<R_EXP>) -> bool {
+impl<const R_EXP: usize, W: Word> PartialEq for Space<R_EXP, W> {
+    fn eq(&self, other: &Space<R_EXP, W>) -> bool {
         self.n == other.n
     }
 }
 
-impl<const R_EXP: usize> Space<R_EXP> {
-    const R : u128 = 1 << R_EXP;
-    const MOD_R: u128 = Self::R - 1;
+impl<const R_EXP: usize, W: Word> Space<R_EXP, W> {
+    /// `R - 1`, the mask that calculates `k mod R`. `R = 2^W::BITS` can't be written down
+    /// directly, but its mask can.
+    const MOD_R: u128 = if R_EXP == 128 { u128::MAX } else { (1 << R_EXP) - 1 };
+
+    /// Whether the product of two residues fits in a single word, in which case we can skip
+    /// the widening multiply entirely.
+    const NARROW: bool = 2 * R_EXP <= W::BITS;
 
     /// Entering the Montgomery "Space" is the first step in the Montgomery multiplication algorithm.
     /// This converts a number `a` into `aR mod N`, where `R = 2^r_exp` and `N` is the modulus.
-    #[inline] pub fn enter(&self, a: u128) -> Elt<R_EXP> {
-        let val = self.redc(a * self.r_squared);
+    #[inline] pub fn enter(&self, a: u128) -> Elt<'_, R_EXP, W> {
+        // `a` is usually below the modulus already, so only pay for the division when it isn't.
+        let n = self.n.to_u128();
+        let a = if a < n { a } else { a % n };
+        let val = self.mul(W::from_u128(a), self.r_squared);
 
         Elt {
             val,
@@ -44,20 +100,103 @@ impl<const R_EXP: usize> Space<R_EXP> {
         }
     }
 
-    #[inline] pub fn factorial(&self, n: u128) -> Elt<R_EXP> {
+    #[inline] pub fn factorial(&self, n: u128) -> Elt<'_, R_EXP, W> {
         let mut result = self.enter(1);
         for i in 1..=n {
-            result = result * self.enter(i as u128);
+            result *= self.enter(i);
+        }
+        result
+    }
+
+    /// `n!` via Wilson's theorem, which is only valid when the modulus `p` is prime and `n < p`
+    /// (otherwise `None`).
+    ///
+    /// Wilson says `(p-1)! = -1 mod p`. Splitting that product at `n`, the top half is
+    ///
+    /// ```text
+    /// (n+1)(n+2)...(p-1) = (-(p-1-n))...(-2)(-1) = (-1)^(p-1-n) (p-1-n)!  mod p
+    /// ```
+    ///
+    /// so `n! = -1 / ((-1)^(p-1-n) (p-1-n)!) = (-1)^(n+1) / (p-1-n)!`, since `p` is odd. That's
+    /// `p - 1 - n` multiplies and one inversion, which is a bargain when `n` is close to `p`.
+    pub fn reflected_factorial(&self, n: u128) -> Option<Elt<'_, R_EXP, W>> {
+        let p = self.n.to_u128();
+        if n >= p { return None; }
+
+        let inv = self.factorial(p - 1 - n).inverse()?;
+
+        if n & 1 == 1 {
+            Some(inv)
+        } else {
+            Some(self.enter(0) - inv)
+        }
+    }
+
+    /// `n!`, by whichever of the forward product or Wilson reflection needs fewer multiplies, or
+    /// `sublinear_factorial` if both are long. The modulus must be prime for the reflection to
+    /// hold.
+    ///
+    /// The solver picks its primes just above the end of each chunk, so the reflected product is
+    /// about as long as the chunk, no matter how far into the search the chunk starts.
+    pub fn prime_factorial(&self, n: u128) -> Elt<'_, R_EXP, W> {
+        let p = self.n.to_u128();
+
+        // p | n! as soon as n reaches p.
+        if n >= p { return self.enter(0); }
+
+        if n.min(p - 1 - n) >= SUBLINEAR_CUTOFF {
+            return self.sublinear_factorial(n);
+        }
+
+        // The inversion costs something like a couple of hundred multiplies' worth, so only
+        // reflect when it saves more than that.
+        if p - 1 - n + REFLECTION_OVERHEAD < n {
+            if let Some(f) = self.reflected_factorial(n) {
+                return f;
+            }
+        }
+
+        self.factorial(n)
+    }
+
+    /// Inverts every element of `elts` with a single modular inversion, or returns `None` if any
+    /// of them isn't a unit.
+    ///
+    /// This is Montgomery's trick: build the running products `p_i = e_0 * ... * e_i`, invert
+    /// only the last, then walk backwards peeling one factor off at a time, since `e_i^-1 =
+    /// p_i^-1 * p_{i-1}` and `p_{i-1}^-1 = p_i^-1 * e_i`. That trades `k` inversions for one
+    /// inversion and `3(k - 1)` multiplies.
+    pub fn batch_inverse<'a>(&'a self, elts: &[Elt<'a, R_EXP, W>]) -> Option<Vec<Elt<'a, R_EXP, W>>> {
+        if elts.is_empty() { return Some(vec![]); }
+
+        let mut prefix = Vec::with_capacity(elts.len());
+        let mut acc = self.enter(1);
+        for &e in elts {
+            acc *= e;
+            prefix.push(acc);
+        }
+
+        let mut inv = acc.inverse()?;
+        let mut out = vec![inv; elts.len()];
+
+        for i in (1..elts.len()).rev() {
+            out[i] = inv * prefix[i - 1];
+            inv *= elts[i];
         }
-        return result;
+        out[0] = inv;
+
+        Some(out)
     }
 
     /// Calculates aRn via aRr * rRn, where rRn is precomputed and cached at creation time.
-    pub fn legendre(&self, a: Elt<R_EXP>) -> LegendreSymbol {
-        let exp = (self.n - 1) >> 1;
-        let result = a.exp(exp);
+    ///
+    /// Panics on a space built by `try_new_bare`, which has neither.
+    pub fn legendre(&self, a: Elt<R_EXP, W>) -> LegendreSymbol {
+        let plan = self.legendre_plan.as_ref()
+            .expect("legendre needs a space built by `try_new`, not `try_new_bare`");
+        let result = a.exp_with(plan);
 
-        let a_r_sym = if result.val == 0 {
+        let a_r_sym = if result.val == W::ZERO {
             LegendreSymbol::Divisor
                 // it's faster to enter than exit, as the latter requires a mod operation, and
                 // entering only requires shifts.
@@ -70,6 +209,54 @@ impl<const R_EXP: usize> Space<R_EXP> {
         a_r_sym * self.r_n_legendre
     }
 
+    /// The same symbol as `legendre`, via the binary Jacobi algorithm instead of Euler's
+    /// criterion. This has to leave the space (one REDC) since the algorithm works on the plain
+    /// integer, but that's far cheaper than the ~2 log2(n) multiplies of the exponentiation.
+    pub fn jacobi(&self, a: Elt<R_EXP, W>) -> LegendreSymbol {
+        jacobi(a.exit(), self.n.to_u128())
+    }
+
+    /// Montgomery multiplication of two values already in the space, `aR * bR -> abR`. Both
+    /// arguments must be reduced mod `n`.
+    ///
+    /// When the modulus is at most half the width of `W` the product fits in a single word, so
+    /// the (much cheaper) native multiply is used. Above that we need the double-width product.
+    #[inline] pub fn mul(&self, a: W, b: W) -> W {
+        Self::mul_with(a, b, self.n, self.n_prime)
+    }
+
+    /// `mul`, with the space's constants passed in directly. This lets callers that keep the
+    /// constants in their own layout (see `SpaceBatch`) use the same arithmetic without holding a
+    /// `Space` for every modulus.
+    #[inline] pub fn mul_with(a: W, b: W, n: W, n_prime: W) -> W {
+        if Self::NARROW {
+            Self::redc_with(a * b, W::ZERO, n, n_prime)
+        } else {
+            let (lo, hi) = a.widening_mul(b);
+            Self::redc_with(lo, hi, n, n_prime)
+        }
+    }
+
+    /// Modular addition of two reduced values. This doesn't care whether they're in Montgomery
+    /// form, since `aR + bR = (a + b)R`.
+    #[inline] pub fn add(&self, a: W, b: W) -> W {
+        Self::add_with(a, b, self.n)
+    }
+
+    #[inline] pub fn add_with(a: W, b: W, n: W) -> W {
+        // `a + b` can overflow the word when `n` is close to its top, so compare against the
+        // distance to `n` instead of adding first.
+        if a >= n - b { a - (n - b) } else { a + b }
+    }
+
+    /// Modular subtraction of two reduced values.
+    #[inline] pub fn sub(&self, a: W, b: W) -> W {
+        Self::sub_with(a, b, self.n)
+    }
+
+    #[inline] pub fn sub_with(a: W, b: W, n: W) -> W {
+        if a >= b { a - b } else { n - (b - a) }
+    }
 
     /// REDC is the core of the Montgomery multiplication algorithm. It takes a number `a` and
     /// quickly reduces it modulo `n` by multiplying it by `n_prime` modulo `r` and then shifting
@@ -77,21 +264,67 @@ impl<const R_EXP: usize> Space<R_EXP> {
     /// modulo `n`.
     ///
     /// This allows a _much_ faster modulo operation, since shifting is much cheaper than division.
-    /// This scales up to multiprecision numbers, but we limit to 128b numbers here.
-    #[inline] pub fn redc(&self, a: u128) -> u128 {
+    /// This scales up to multiprecision numbers, but we limit to a single word here.
+    #[inline] pub fn redc(&self, a: W) -> W {
+        self.redc_wide(a, W::ZERO)
+    }
+
+    /// REDC over a double-width input `hi * 2^W::BITS + lo`, which must be less than `nR`.
+    ///
+    /// This is the same algorithm as `redc`, but `T + mn` can need up to `2 * R_EXP + 1` bits,
+    /// which overflows a single word as soon as `R_EXP` is half its width. The sum is carried
+    /// across `lo`, `hi` and a final carry bit, and the shift by `R_EXP` pulls the result back out
+    /// of that triple.
+    #[inline] pub fn redc_wide(&self, lo: W, hi: W) -> W {
+        Self::redc_with(lo, hi, self.n, self.n_prime)
+    }
+
+    /// `redc_wide`, with the space's constants passed in directly.
+    #[inline] pub fn redc_with(lo: W, hi: W, n: W, n_prime: W) -> W {
         // k mod r, since r is a power of two, is just the `r_exp` least significant bits of k.
         // that can be calculated by `k & (r - 1)`. This is equivalent to `k % r` when `r` is a
         // power of two.
-        let little_m = ((a & Self::MOD_R) * self.n_prime) & Self::MOD_R; 
-        let new_t = (a + (little_m * self.n)) >> R_EXP;
+        let little_m = lo.wrapping_mul(n_prime) & W::from_u128(Self::MOD_R);
+
+        let (mn_lo, mn_hi) = if Self::NARROW {
+            (little_m * n, W::ZERO)
+        } else {
+            little_m.widening_mul(n)
+        };
+
+        let (sum_lo, carry_lo) = lo.overflowing_add(mn_lo);
+        let (sum_hi, carry_hi) = hi.overflowing_add(mn_hi);
+        let (sum_hi, carry_mid) = sum_hi.overflowing_add(if carry_lo { W::ONE } else { W::ZERO });
+        // Only possible when R_EXP is the full width, since otherwise `T + mn < 2nR` fits.
+        let carry = carry_hi || carry_mid;
+
+        // `(T + mn) / R`; the low `R_EXP` bits are zero by construction of `little_m`.
+        let new_t = if R_EXP == W::BITS {
+            sum_hi
+        } else {
+            (sum_lo >> R_EXP) | (sum_hi << (W::BITS - R_EXP))
+        };
 
-        if new_t >= self.n {
-            new_t - self.n
+        // new_t < 2n, so at most one subtraction brings it into range. If the sum carried out of
+        // the double word, the true value is `2^W::BITS + new_t`, which is certainly larger than
+        // `n`.
+        if carry || new_t >= n {
+            new_t.wrapping_sub(n)
         } else {
             new_t
         }
     }
 
+    /// `R mod n`, which is also `1` entered into the space.
+    fn r_mod_n(n: u128) -> u128 {
+        if R_EXP == 128 {
+            // 2^128 = (2^128 - 1) + 1
+            (u128::MAX % n + 1) % n
+        } else {
+            (1 << R_EXP) % n
+        }
+    }
+
     /// n is the modulus, r_exp is the exponent of the Montgomery constant r = 2^r_exp.
     /// This function calculates all other relevant constants, in particular it calculates:
     ///
@@ -101,11 +334,69 @@ impl<const R_EXP: usize> Space<R_EXP> {
     /// n_inv     = n^-1 mod r           // The modular inverse of n mod r
     /// n_prime   = (r - n)^-1 mod r     // The modular inverse of -n mod r, used in `redc`
     ///
+    /// The constants are all worked out in `u128` and narrowed to the word at the end, since this
+    /// only happens once per space.
     ///
-    pub fn new(n: u128) -> Space<R_EXP> {
-        let r_inv = mod_inverse(Self::R, n).unwrap() % n;
-        let n_inv = mod_inverse(n, Self::R).unwrap() & Self::MOD_R;
-        let r_squared = mod_mult(Self::R, Self::R, n);
+    /// Panics if `n` isn't a valid modulus for this space; see `try_new`.
+    pub fn new(n: u128) -> Space<R_EXP, W> {
+        Self::try_new(n).unwrap_or_else(|e| panic!("invalid montgomery space: {}", e))
+    }
+
+    /// Builds the space, or reports which precondition `n` violates: it must be odd, greater than
+    /// 1, and below `R`, and `R` itself must fit in the word.
+    pub fn try_new(n: u128) -> Result<Space<R_EXP, W>, SpaceError> {
+        let mut space = Self::try_new_bare(n)?;
+
+        // This is used in the speedup of the legendre symbol calculation in #legendre.
+        // We have to use the naive calculation here, but we only do this once and cache it.
+        // Then the legendre symbol can be calculated as (aRr * rRn). This is a speedup because
+        // we're doing the expensive operation mod R, and R is really easy to divide by.
+        space.r_n_legendre = LegendreSymbol::naive_legendre(Self::r_mod_n(n), n);
+
+        // Every legendre test raises to the same power, so the windows only need working out once.
+        space.legendre_plan = Some(ExpPlan::new((n - 1) >> 1));
+
+        Ok(space)
+    }
+
+    /// Whether `n` can make a space, without building one: the checks `try_new` starts with.
+    pub fn check_modulus(n: u128) -> Result<(), SpaceError> {
+        if R_EXP == 0 || R_EXP > W::BITS {
+            return Err(SpaceError::InvalidRadix { r_exp: R_EXP, word_bits: W::BITS });
+        }
+        if n == 0 { return Err(SpaceError::ZeroModulus); }
+        if n == 1 { return Err(SpaceError::UnitModulus); }
+        if n & 1 == 0 { return Err(SpaceError::EvenModulus(n)); }
+        if R_EXP < 128 && n >> R_EXP != 0 {
+            return Err(SpaceError::ModulusTooLarge { n, r_exp: R_EXP });
+        }
+        Ok(())
+    }
+
+    /// `try_new_bare`, panicking on an invalid modulus like `new`.
+    pub fn new_bare(n: u128) -> Space<R_EXP, W> {
+        Self::try_new_bare(n).unwrap_or_else(|e| panic!("invalid montgomery space: {}", e))
+    }
+
+    /// A space for the arithmetic alone, without the constants `legendre` needs: those cost a
+    /// full `mod_exp` to set up, which is more than callers that build a space for a handful of
+    /// multiplications (a Miller-Rabin test, one factorial) ever get back. `legendre` panics on
+    /// it.
+    pub fn try_new_bare(n: u128) -> Result<Space<R_EXP, W>, SpaceError> {
+        Self::check_modulus(n)?;
+
+        let r_mod_n = Self::r_mod_n(n);
+        let r_squared = mod_mult(r_mod_n, r_mod_n, n);
+
+        // n_inv is found by Newton's iteration rather than the extended GCD, since the latter
+        // would need `r` itself, which doesn't fit when `R_EXP = 128`. For odd `n`, `n * n = 1 mod
+        // 8`, so `n` is its own inverse to 3 bits, and each step of `x <- x(2 - nx)` doubles the
+        // number of correct bits. Seven steps gets us past 128.
+        let mut inv = n;
+        for _ in 0..7 {
+            inv = inv.wrapping_mul(2u128.wrapping_sub(n.wrapping_mul(inv)));
+        }
+        let n_inv = inv & Self::MOD_R;
 
         // n_prime is _not_ the modular inverse of n mod r. Since we're operating unsigned, we
         // can't rely on the extended GCD to calculate it, fortunately it's easy to recover without
@@ -116,23 +407,22 @@ impl<const R_EXP: usize> Space<R_EXP> {
         // => nn' = -1        mod r
         // => n(-n^-1) = 1    mod r
         // => n' = -n^-1      mod r
-        // => n' = (r - n)^-1 mod r  // Since n < r, we know this will never underflow in the subtraction.
         //
-        let n_prime = mod_inverse(Self::R - n, Self::R).unwrap() & Self::MOD_R;
+        // and negation mod a power of two is just a wrapping negation followed by the mask.
+        let n_prime = inv.wrapping_neg() & Self::MOD_R;
 
-        // This is used in the speedup of the legendre symbol calculation in #legendre.
-        // We have to use the naive calculation here, but we only do this once and cache it.
-        // Then the legendre symbol can be calculated as (aRr * rRn). This is a speedup because
-        // we're doing the expensive operation mod R, and R is really easy to divide by.
-        let r_n_legendre = LegendreSymbol::naive_legendre(Self::R, n);
+        let (n, n_prime) = (W::from_u128(n), W::from_u128(n_prime));
 
-        Space {
-            r_inv,
-            r_n_legendre,
-            r_squared,
+        Ok(Space {
+            // A single REDC divides by R, so reducing `1` gives R^-1 without an inverse.
+            r_inv: Self::redc_with(W::ONE, W::ZERO, n, n_prime),
+            // `try_new` fills these in.
+            r_n_legendre: LegendreSymbol::Divisor,
+            r_squared: W::from_u128(r_squared),
             n,
-            n_inv,
-            n_prime
-        }
+            n_inv: W::from_u128(n_inv),
+            n_prime,
+            legendre_plan: None,
+        })
     }
 }
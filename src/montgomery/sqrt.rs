@@ -0,0 +1,122 @@
+use crate::math::legendre::LegendreSymbol;
+use crate::montgomery::{Elt, Space, Word};
+
+// Tonelli-Shanks spends O(S^2) multiplies in its inner loop, where `p - 1 = Q * 2^S`, on top of
+// the O(log p) shared with Cipolla. Cipolla's cost doesn't depend on `S`, but each of its steps
+// is a multiply in F_{p^2}, roughly four times the price of one in F_p. So Tonelli-Shanks wins
+// until `S^2` gets to be a few times `log p`.
+const CIPOLLA_FACTOR: u32 = 4;
+
+impl<const R_EXP: usize, W: Word> Space<R_EXP, W> {
+    /// The smallest quadratic nonresidue in the space, found by trial. Under GRH there's one below
+    /// `2 ln(p)^2`, and in practice it's almost always tiny.
+    fn find_nonresidue(&self) -> Elt<'_, R_EXP, W> {
+        (2..).map(|z| self.enter(z))
+             .find(|z| z.legendre() == LegendreSymbol::Nonresidue)
+             .unwrap()
+    }
+}
+
+impl<'a, const R_EXP: usize, W: Word> Elt<'a, R_EXP, W> {
+    /// Both square roots mod the (odd prime) modulus, smallest first, or `None` if this isn't a
+    /// quadratic residue. Zero is its own (double) root.
+    ///
+    /// Dispatches to Tonelli-Shanks, unless the power of two dividing `p - 1` is large enough that
+    /// Cipolla is cheaper.
+    pub fn sqrt(&self) -> Option<(Elt<'a, R_EXP, W>, Elt<'a, R_EXP, W>)> {
+        let p = self.space.n.to_u128();
+        let s = (p - 1).trailing_zeros();
+        let bits = 128 - p.leading_zeros();
+
+        if s * s > CIPOLLA_FACTOR * bits {
+            self.sqrt_cipolla()
+        } else {
+            self.sqrt_tonelli_shanks()
+        }
+    }
+
+    /// Tonelli-Shanks. Writing `p - 1 = Q * 2^S`, `a^((Q+1)/2)` is a root of `a` up to a factor
+    /// living in the 2-Sylow subgroup; the loop corrects that factor one power of two at a time
+    /// using powers of a fixed nonresidue.
+    pub fn sqrt_tonelli_shanks(&self) -> Option<(Elt<'a, R_EXP, W>, Elt<'a, R_EXP, W>)> {
+        let space = self.space;
+
+        match self.legendre() {
+            LegendreSymbol::Divisor => return Some((*self, *self)),
+            LegendreSymbol::Nonresidue => return None,
+            LegendreSymbol::Residue => {}
+        }
+
+        let one = space.enter(1);
+        let p = space.n.to_u128();
+        let s = (p - 1).trailing_zeros();
+        let q = (p - 1) >> s;
+
+        let mut m = s;
+        let mut c = space.find_nonresidue().exp(q);
+        let mut t = self.exp(q);
+        let mut r = self.exp((q + 1) >> 1);
+
+        while t != one {
+            // The least i with t^(2^i) = 1. Since t is in a group of order 2^m, i < m.
+            let mut i = 0;
+            let mut t_2i = t;
+            while t_2i != one {
+                t_2i = t_2i * t_2i;
+                i += 1;
+            }
+
+            let mut b = c;
+            for _ in 0..(m - i - 1) {
+                b = b * b;
+            }
+
+            m = i;
+            c = b * b;
+            t *= c;
+            r *= b;
+        }
+
+        Some(Self::ordered(r))
+    }
+
+    /// Cipolla. Find `a` such that `w = a^2 - n` is a nonresidue, then `(a + sqrt(w))^((p+1)/2)`,
+    /// computed in `F_p[sqrt(w)]`, lands back in `F_p` and squares to `n`.
+    pub fn sqrt_cipolla(&self) -> Option<(Elt<'a, R_EXP, W>, Elt<'a, R_EXP, W>)> {
+        let space = self.space;
+
+        match self.legendre() {
+            LegendreSymbol::Divisor => return Some((*self, *self)),
+            LegendreSymbol::Nonresidue => return None,
+            LegendreSymbol::Residue => {}
+        }
+
+        let (a, w) = (0..).map(|a| space.enter(a))
+                          .map(|a| (a, a * a - *self))
+                          .find(|(_, w)| w.legendre() == LegendreSymbol::Nonresidue)
+                          .unwrap();
+
+        // (x1 + y1 sqrt(w)) (x2 + y2 sqrt(w)) = (x1 x2 + y1 y2 w) + (x1 y2 + x2 y1) sqrt(w)
+        let mul = |(x1, y1): (Elt<'a, R_EXP, W>, Elt<'a, R_EXP, W>), (x2, y2): (Elt<'a, R_EXP, W>, Elt<'a, R_EXP, W>)| {
+            (x1 * x2 + y1 * y2 * w, x1 * y2 + x2 * y1)
+        };
+
+        let mut acc = (space.enter(1), space.enter(0));
+        let mut base = (a, space.enter(1));
+        let mut exp = (space.n.to_u128() >> 1) + 1;
+
+        while exp > 0 {
+            if exp & 1 == 1 { acc = mul(acc, base); }
+            base = mul(base, base);
+            exp >>= 1;
+        }
+
+        Some(Self::ordered(acc.0))
+    }
+
+    /// `(r, -r)`, smallest first.
+    fn ordered(r: Elt<'a, R_EXP, W>) -> (Elt<'a, R_EXP, W>, Elt<'a, R_EXP, W>) {
+        let neg = r.space.enter(0) - r;
+        if r.exit() <= neg.exit() { (r, neg) } else { (neg, r) }
+    }
+}
@@ -1,24 +1,34 @@
+//! Shared test inputs and helpers.
+//!
+//! quickcheck only produces integers up to its size parameter, which never gets close to the
+//! interesting end of a 128b modulus. Props that need the whole `u128` range take a
+//! `WideTestCase` instead of plain integers.
+
 use crate::math::gcd;
 use quickcheck::{Arbitrary, Gen};
+use rand::Rng;
+use std::io;
 
 #[derive(Clone, Copy, Debug)]
 pub struct TestCase {
     pub(crate) a: u128,
     pub(crate) b: u128,
     pub(crate) n: u128,
+    #[allow(dead_code)]
     pub(crate) r_exp: usize
 }
 
 impl Arbitrary for TestCase {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
-        // r_exp is in [1,127] to avoid overflows
-        let r_exp = 1 + usize::arbitrary(g) % 126;
+        // r_exp is in [2,126] to avoid overflows, and so that there's room for an `n` above 1
+        let r_exp = 2 + usize::arbitrary(g) % 125;
         let r = 1 << r_exp;
 
-        // We'll just hunt till we find a coprime `n`, should be fast, any odd number will
-        // do. We also need `n < r`, so we can just examine `n mod r` to ensure this.
+        // We'll just hunt till we find a coprime `n`, should be fast, any odd number but 1 will
+        // do (see `SpaceError`). We also need `n < r`, so we can just examine `n mod r` to ensure
+        // this.
         let mut n = u128::arbitrary(g) % r;
-        while gcd(n, r) != 1 {
+        while gcd(n, r) != 1 || n == 1 {
             n = u128::arbitrary(g) % r;
         }
 
@@ -29,3 +39,35 @@ impl Arbitrary for TestCase {
         TestCase { a, b, n, r_exp }
     }
 }
+
+/// A test case drawn from the full `u128` range, with an odd modulus, for exercising `Space<128>`.
+#[derive(Clone, Copy, Debug)]
+pub struct WideTestCase {
+    pub(crate) a: u128,
+    pub(crate) b: u128,
+    pub(crate) n: u128
+}
+
+impl Arbitrary for WideTestCase {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        // Setting the top bit keeps us in the range that a narrower space can't represent.
+        let n = g.gen::<u128>() | 1 | (1 << 127);
+
+        let a = g.gen::<u128>() % n;
+        let b = g.gen::<u128>() % n;
+
+        WideTestCase { a, b, n }
+    }
+}
+
+/// Writes to a scratch file with `write`, reads it back with `read`, and removes it. The name
+/// carries `name` and the process id, so tests running side by side don't share a file.
+pub fn through_file<T>(name: &str, write: impl FnOnce(&str) -> io::Result<()>, read: impl FnOnce(&str) -> io::Result<T>) -> T {
+    let path = std::env::temp_dir().join(format!("brocard-{}-{}.txt", name, std::process::id()));
+    let path = path.to_str().unwrap();
+
+    write(path).unwrap();
+    let restored = read(path);
+    std::fs::remove_file(path).unwrap();
+    restored.unwrap()
+}
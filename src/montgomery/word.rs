@@ -0,0 +1,78 @@
+use crate::math::widening_mul;
+use std::fmt::Debug;
+use std::ops::{Add, Sub, Mul, Rem, BitAnd, BitOr, Shl, Shr};
+
+/// A machine word that a Montgomery `Space` can store its residues in.
+///
+/// Everything outside the hot loop (building a space, entering values, exiting them) is done in
+/// `u128`, since that's the type the rest of the crate speaks. The word only matters for `mul`
+/// and `redc`, where we want the narrowest type that holds the modulus; a u64 residue multiplies
+/// with a single native 64x64->128 instruction, where a u128 residue needs four of them.
+///
+/// Each word knows how to produce its own double-width product as a `(lo, hi)` pair, which is all
+/// REDC needs to be written once for every width.
+pub trait Word:
+    Copy + Debug + PartialEq + Eq + PartialOrd + Ord + Send + Sync + 'static
+    + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Rem<Output = Self>
+    + BitAnd<Output = Self> + BitOr<Output = Self>
+    + Shl<usize, Output = Self> + Shr<usize, Output = Self>
+{
+    const BITS: usize;
+    const ZERO: Self;
+    const ONE: Self;
+    const MAX: Self;
+
+    /// Truncates `v` to the word. Callers are expected to have checked it fits.
+    fn from_u128(v: u128) -> Self;
+    fn to_u128(self) -> u128;
+
+    fn wrapping_mul(self, rhs: Self) -> Self;
+    fn wrapping_sub(self, rhs: Self) -> Self;
+    fn overflowing_add(self, rhs: Self) -> (Self, bool);
+
+    /// The full double-width product, as `(lo, hi)`.
+    fn widening_mul(self, rhs: Self) -> (Self, Self);
+}
+
+macro_rules! impl_word {
+    ($word:ty, $double:ty) => {
+        impl Word for $word {
+            const BITS: usize = <$word>::BITS as usize;
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            const MAX: Self = <$word>::MAX;
+
+            #[inline] fn from_u128(v: u128) -> Self { v as $word }
+            #[inline] fn to_u128(self) -> u128 { self as u128 }
+
+            #[inline] fn wrapping_mul(self, rhs: Self) -> Self { <$word>::wrapping_mul(self, rhs) }
+            #[inline] fn wrapping_sub(self, rhs: Self) -> Self { <$word>::wrapping_sub(self, rhs) }
+            #[inline] fn overflowing_add(self, rhs: Self) -> (Self, bool) { <$word>::overflowing_add(self, rhs) }
+
+            #[inline] fn widening_mul(self, rhs: Self) -> (Self, Self) {
+                let product = (self as $double) * (rhs as $double);
+                (product as $word, (product >> <$word>::BITS) as $word)
+            }
+        }
+    };
+}
+
+impl_word!(u32, u64);
+impl_word!(u64, u128);
+
+impl Word for u128 {
+    const BITS: usize = 128;
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    const MAX: Self = u128::MAX;
+
+    #[inline] fn from_u128(v: u128) -> Self { v }
+    #[inline] fn to_u128(self) -> u128 { self }
+
+    #[inline] fn wrapping_mul(self, rhs: Self) -> Self { u128::wrapping_mul(self, rhs) }
+    #[inline] fn wrapping_sub(self, rhs: Self) -> Self { u128::wrapping_sub(self, rhs) }
+    #[inline] fn overflowing_add(self, rhs: Self) -> (Self, bool) { u128::overflowing_add(self, rhs) }
+
+    // There's no native 256b type, so this is done on 64b limbs.
+    #[inline] fn widening_mul(self, rhs: Self) -> (Self, Self) { widening_mul(self, rhs) }
+}
@@ -0,0 +1,70 @@
+use crate::math::{mod_add, widening_mul, legendre::LegendreSymbol};
+use crate::montgomery::SpaceError;
+use crate::ring::ModRing;
+
+/// Barrett reduction, for moduli below 2^64.
+///
+/// Rather than dividing the product `x = ab` by `n`, Barrett precomputes `m = floor((2^128 - 1) /
+/// n)` and estimates the quotient as the top half of `xm`. Since `m` undershoots `2^128 / n`, so
+/// does the estimate, but by no more than a few, so `x - qn` is a handful of subtractions away
+/// from the remainder. That's a 128x128 high multiply and a 64x64 multiply in place of a division,
+/// and unlike Montgomery the residues stay as they are, so there's nothing to enter or exit.
+#[derive(Debug, Clone)]
+pub struct BarrettSpace {
+    pub n: u64,
+    pub m: u128,
+}
+
+impl BarrettSpace {
+    #[inline] fn reduce(&self, x: u128) -> u64 {
+        let n = self.n as u128;
+        let q = widening_mul(x, self.m).1;
+
+        // q <= x / n, so this doesn't underflow.
+        let mut r = x - q * n;
+        while r >= n {
+            r -= n;
+        }
+        r as u64
+    }
+}
+
+impl ModRing for BarrettSpace {
+    type Elt = u64;
+
+    fn try_new(n: u128) -> Result<Self, SpaceError> {
+        if n == 0 { return Err(SpaceError::ZeroModulus); }
+        if n == 1 { return Err(SpaceError::UnitModulus); }
+        if n >> 64 != 0 { return Err(SpaceError::ModulusTooLarge { n, r_exp: 64 }); }
+
+        Ok(BarrettSpace { n: n as u64, m: u128::MAX / n })
+    }
+
+    #[inline] fn modulus(&self) -> u128 {
+        self.n as u128
+    }
+
+    #[inline] fn enter(&self, a: u128) -> u64 {
+        (a % self.n as u128) as u64
+    }
+
+    #[inline] fn exit(&self, a: u64) -> u128 {
+        a as u128
+    }
+
+    #[inline] fn mul(&self, a: u64, b: u64) -> u64 {
+        self.reduce(a as u128 * b as u128)
+    }
+
+    #[inline] fn add(&self, a: u64, b: u64) -> u64 {
+        mod_add(a as u128, b as u128, self.n as u128) as u64
+    }
+
+    fn legendre(&self, a: u64) -> LegendreSymbol {
+        match self.exp(a, (self.n as u128 - 1) >> 1) {
+            0 => LegendreSymbol::Divisor,
+            1 => LegendreSymbol::Residue,
+            _ => LegendreSymbol::Nonresidue,
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::math::legendre::{jacobi, LegendreSymbol};
+use crate::montgomery::SpaceError;
+use std::fmt::Debug;
+
+pub mod montgomery;
+pub mod barrett;
+pub mod naive;
+
+pub use barrett::BarrettSpace;
+pub use naive::NaiveSpace;
+
+/// Arithmetic modulo a fixed `n`, independent of how the reduction is done.
+///
+/// Each ring keeps its residues in whatever representation suits its reduction (Montgomery form,
+/// plain residues, ...), as `Self::Elt`. Values only mean anything to the ring that made them, and
+/// the plain integer is only recovered by `exit`. This is what lets `BrocardSpan` be written once
+/// and run over `montgomery::Space`, `BarrettSpace` and `NaiveSpace` for comparison.
+///
+/// The trait's methods share names with the inherent methods on `montgomery::Space`, which take
+/// precedence, so code that works with a concrete `Space` is unaffected.
+pub trait ModRing: Sized {
+    type Elt: Copy + Debug + PartialEq;
+
+    /// Builds the ring, or reports why `n` can't be used as its modulus.
+    fn try_new(n: u128) -> Result<Self, SpaceError>;
+
+    fn modulus(&self) -> u128;
+
+    fn enter(&self, a: u128) -> Self::Elt;
+    fn exit(&self, a: Self::Elt) -> u128;
+
+    fn mul(&self, a: Self::Elt, b: Self::Elt) -> Self::Elt;
+    fn add(&self, a: Self::Elt, b: Self::Elt) -> Self::Elt;
+
+    /// `(a/n)` for a prime modulus.
+    fn legendre(&self, a: Self::Elt) -> LegendreSymbol;
+
+    /// The same symbol as `legendre`, by the binary Jacobi algorithm on the plain integer.
+    fn jacobi(&self, a: Self::Elt) -> LegendreSymbol {
+        jacobi(self.exit(a), self.modulus())
+    }
+
+    fn exp(&self, a: Self::Elt, e: u128) -> Self::Elt {
+        let mut acc = self.enter(1);
+        let mut base = a;
+        let mut e = e;
+        while e > 0 {
+            if e & 1 == 1 { acc = self.mul(acc, base); }
+            base = self.mul(base, base);
+            e >>= 1;
+        }
+        acc
+    }
+
+    fn factorial(&self, n: u128) -> Self::Elt {
+        (1..=n).fold(self.enter(1), |acc, i| self.mul(acc, self.enter(i)))
+    }
+
+    /// `n!` for a prime modulus, by Wilson reflection when that's the shorter product (see
+    /// `montgomery::Space::reflected_factorial`). The inversion is by Fermat, which costs an
+    /// exponentiation.
+    fn prime_factorial(&self, n: u128) -> Self::Elt {
+        let p = self.modulus();
+        if n >= p { return self.enter(0); }
+        if p - 1 - n >= n { return self.factorial(n); }
+
+        let inv = self.exp(self.factorial(p - 1 - n), p - 2);
+        if n & 1 == 1 {
+            inv
+        } else {
+            self.enter(p - self.exit(inv))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::mod_mult;
+    use crate::math::prime::primes_from;
+    use crate::montgomery::{Space, TestCase};
+
+    // Barrett only takes moduli below 2^64, and Montgomery only odd ones, so fold the modulus down.
+    fn narrow(tc: TestCase) -> TestCase {
+        let n = (tc.n % (1 << 63)) | 1;
+        let n = if n == 1 { 3 } else { n };
+        TestCase { a: tc.a % n, b: tc.b % n, n, r_exp: 64 }
+    }
+
+    fn check<R: ModRing>(tc: TestCase) -> bool {
+        let TestCase { a, b, n, r_exp: _ } = tc;
+        let ring = R::try_new(n).unwrap();
+        let (x, y) = (ring.enter(a), ring.enter(b));
+
+        ring.exit(ring.mul(x, y)) == mod_mult(a, b, n)
+            && ring.exit(ring.add(x, y)) == (a + b) % n
+            && ring.exit(ring.factorial(20)) == (1..=20).fold(1, |acc, i| mod_mult(acc, i, n))
+    }
+
+    #[quickcheck]
+    fn every_ring_is_correct(tc: TestCase) -> bool {
+        let tc = narrow(tc);
+
+        check::<Space<64, u64>>(tc) && check::<BarrettSpace>(tc) && check::<NaiveSpace>(tc)
+    }
+
+    #[quickcheck]
+    fn every_ring_agrees_on_legendre(a: u128, which: u8) -> bool {
+        let p = primes_from(3).nth(which as usize).unwrap();
+
+        let montgomery = Space::<64, u64>::try_new(p).unwrap();
+        let barrett = BarrettSpace::try_new(p).unwrap();
+        let naive = NaiveSpace::try_new(p).unwrap();
+
+        let expected = LegendreSymbol::naive_legendre(a, p);
+
+        ModRing::legendre(&montgomery, ModRing::enter(&montgomery, a)) == expected
+            && barrett.legendre(barrett.enter(a)) == expected
+            && naive.legendre(naive.enter(a)) == expected
+            && barrett.jacobi(barrett.enter(a)) == expected
+    }
+
+    #[quickcheck]
+    fn every_ring_agrees_on_prime_factorial(n: u16, which: u8) -> bool {
+        let p = primes_from(3).nth(which as usize).unwrap();
+        let n = n as u128 % (2 * p);
+
+        let expected = Space::<64, u64>::new(p).factorial(n).exit();
+
+        BarrettSpace::try_new(p).map(|r| r.exit(r.prime_factorial(n))) == Ok(expected)
+            && NaiveSpace::try_new(p).map(|r| r.exit(r.prime_factorial(n))) == Ok(expected)
+    }
+
+    #[test]
+    fn barrett_rejects_wide_moduli() {
+        let n = (1 << 64) + 13;
+        assert_eq!(BarrettSpace::try_new(n).err(), Some(SpaceError::ModulusTooLarge { n, r_exp: 64 }));
+    }
+}
@@ -0,0 +1,53 @@
+use crate::math::legendre::LegendreSymbol;
+use crate::montgomery::{Elt, Space, SpaceError, Word};
+use crate::ring::ModRing;
+
+/// The ring's elements are the raw Montgomery-form words, so every method is a thin wrapper over
+/// the inherent one.
+impl<const R_EXP: usize, W: Word> ModRing for Space<R_EXP, W> {
+    type Elt = W;
+
+    fn try_new(n: u128) -> Result<Self, SpaceError> {
+        Space::try_new(n)
+    }
+
+    #[inline] fn modulus(&self) -> u128 {
+        self.n.to_u128()
+    }
+
+    #[inline] fn enter(&self, a: u128) -> W {
+        Space::enter(self, a).val
+    }
+
+    #[inline] fn exit(&self, a: W) -> u128 {
+        Elt { val: a, space: self }.exit()
+    }
+
+    #[inline] fn mul(&self, a: W, b: W) -> W {
+        Space::mul(self, a, b)
+    }
+
+    #[inline] fn add(&self, a: W, b: W) -> W {
+        Space::add(self, a, b)
+    }
+
+    #[inline] fn legendre(&self, a: W) -> LegendreSymbol {
+        Space::legendre(self, Elt { val: a, space: self })
+    }
+
+    #[inline] fn jacobi(&self, a: W) -> LegendreSymbol {
+        Space::jacobi(self, Elt { val: a, space: self })
+    }
+
+    fn exp(&self, a: W, e: u128) -> W {
+        Elt { val: a, space: self }.exp(e).val
+    }
+
+    fn factorial(&self, n: u128) -> W {
+        Space::factorial(self, n).val
+    }
+
+    fn prime_factorial(&self, n: u128) -> W {
+        Space::prime_factorial(self, n).val
+    }
+}
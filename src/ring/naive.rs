@@ -0,0 +1,45 @@
+use crate::math::{mod_add, mod_mult, legendre::LegendreSymbol};
+use crate::montgomery::SpaceError;
+use crate::ring::ModRing;
+
+/// The reference ring: plain residues, with every operation done by the functions in `math`.
+/// Slow, but simple enough to be obviously right, which is the point.
+#[derive(Debug, Clone)]
+pub struct NaiveSpace {
+    pub n: u128,
+}
+
+impl ModRing for NaiveSpace {
+    type Elt = u128;
+
+    fn try_new(n: u128) -> Result<Self, SpaceError> {
+        if n == 0 { return Err(SpaceError::ZeroModulus); }
+        if n == 1 { return Err(SpaceError::UnitModulus); }
+
+        Ok(NaiveSpace { n })
+    }
+
+    #[inline] fn modulus(&self) -> u128 {
+        self.n
+    }
+
+    #[inline] fn enter(&self, a: u128) -> u128 {
+        a % self.n
+    }
+
+    #[inline] fn exit(&self, a: u128) -> u128 {
+        a
+    }
+
+    #[inline] fn mul(&self, a: u128, b: u128) -> u128 {
+        mod_mult(a, b, self.n)
+    }
+
+    #[inline] fn add(&self, a: u128, b: u128) -> u128 {
+        mod_add(a, b, self.n)
+    }
+
+    fn legendre(&self, a: u128) -> LegendreSymbol {
+        LegendreSymbol::naive_legendre(a, self.n)
+    }
+}